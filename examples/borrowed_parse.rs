@@ -0,0 +1,64 @@
+//! Compare the allocations of the `String` based parsing and the borrowed parsing
+//!
+//! Run with `cargo run --release --example borrowed_parse [KEYFILE]`
+use osshkeys::error::OsshResult;
+use osshkeys::PublicKey;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::env;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const ROUNDS: usize = 10000;
+
+fn measure<F: FnMut() -> OsshResult<()>>(name: &str, mut f: F) -> OsshResult<()> {
+    let start_allocs = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f()?;
+    }
+    let elapsed = start.elapsed();
+    let allocs = ALLOCATIONS.load(Ordering::Relaxed) - start_allocs;
+    println!(
+        "{:>10}: {:>8.2} allocations/key, {:?}/key",
+        name,
+        allocs as f64 / ROUNDS as f64,
+        elapsed / ROUNDS as u32
+    );
+    Ok(())
+}
+
+fn main() -> OsshResult<()> {
+    let path = env::args().nth(1).unwrap_or_else(|| {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/assets/openssh_ed25519.pub").into()
+    });
+    let data = fs::read(path)?;
+
+    measure("String", || {
+        let keystr = String::from_utf8_lossy(&data).into_owned();
+        PublicKey::from_keystr(&keystr)?;
+        Ok(())
+    })?;
+    measure("borrowed", || {
+        PublicKey::from_keybytes(&data)?;
+        Ok(())
+    })?;
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use crate::error::*;
+use crate::format::ossh_pubkey::decode_ossh_pubkey_blob;
+use crate::keys::rsa::RsaSignature;
+use crate::keys::{KeyPair, PublicKey, PublicKeyType, PublicParts};
+use crate::sshbuf::{SshReadExt, SshWriteExt};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use std::env;
+use std::io::{Cursor, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+/// The environment variable containing the path of the agent socket
+pub const AUTH_SOCK_ENV: &str = "SSH_AUTH_SOCK";
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+const SSH_AGENT_RSA_SHA2_256: u32 = 0x02;
+const SSH_AGENT_RSA_SHA2_512: u32 = 0x04;
+
+// The same limit as OpenSSH's agent
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+/// A client talking to the SSH agent over the unix socket
+///
+/// See [draft-miller-ssh-agent](https://datatracker.ietf.org/doc/html/draft-miller-ssh-agent) for the protocol.
+#[derive(Debug)]
+pub struct Client {
+    stream: UnixStream,
+}
+
+impl Client {
+    /// Connect to the agent listening on the socket path
+    pub fn connect<P: AsRef<Path>>(path: P) -> OsshResult<Self> {
+        Ok(Client {
+            stream: UnixStream::connect(path)?,
+        })
+    }
+
+    /// Connect to the agent specified by the `SSH_AUTH_SOCK` environment variable
+    pub fn connect_env() -> OsshResult<Self> {
+        let path = env::var_os(AUTH_SOCK_ENV).ok_or(ErrorKind::InvalidArgument)?;
+        Self::connect(path)
+    }
+
+    /// List the public keys and their comments held by the agent
+    ///
+    /// The keys with unsupported types (e.g. certificates) are skipped.
+    pub fn request_identities(&mut self) -> OsshResult<Vec<(PublicKey, String)>> {
+        let mut reader = self.request(SSH_AGENTC_REQUEST_IDENTITIES, &[])?;
+        if reader.read_u8()? != SSH_AGENT_IDENTITIES_ANSWER {
+            return Err(ErrorKind::AgentFailure.into());
+        }
+
+        let nkeys = reader.read_uint32()?;
+        let mut identities = Vec::new();
+        for _ in 0..nkeys {
+            let blob = reader.read_string()?;
+            let comment = reader.read_utf8()?;
+            match decode_ossh_pubkey_blob(&blob) {
+                Ok(mut pubkey) => {
+                    *pubkey.comment_mut() = comment.clone();
+                    identities.push((pubkey, comment));
+                }
+                Err(e) if e.kind() == ErrorKind::UnsupportType => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(identities)
+    }
+
+    /// Ask the agent to sign the data with the private key of the public key
+    ///
+    /// The RSA signature algorithm is decided by the
+    /// [`RsaPublicKey::sign_type()`](../keys/rsa/struct.RsaPublicKey.html#method.sign_type) of the key.
+    ///
+    /// Returns the signature blob in the SSH wire format, which contains the algorithm name and the signature.
+    pub fn sign(&mut self, pubkey: &PublicKey, data: &[u8]) -> OsshResult<Vec<u8>> {
+        self.sign_with_alg(pubkey, data, None)
+    }
+
+    /// Ask the agent to sign the data, overriding the RSA signature algorithm
+    ///
+    /// This is the same as [`sign()`](struct.Client.html#method.sign),
+    /// except that `alg` is used instead of the signature hash of the RSA key if it is set.
+    pub fn sign_with_alg(
+        &mut self,
+        pubkey: &PublicKey,
+        data: &[u8],
+        alg: Option<RsaSignature>,
+    ) -> OsshResult<Vec<u8>> {
+        let flags = match &pubkey.key {
+            PublicKeyType::RSA(key) => match alg.unwrap_or_else(|| key.sign_type()) {
+                RsaSignature::SHA1 => 0,
+                RsaSignature::SHA2_256 => SSH_AGENT_RSA_SHA2_256,
+                RsaSignature::SHA2_512 => SSH_AGENT_RSA_SHA2_512,
+            },
+            _ => 0,
+        };
+
+        let mut payload = Vec::new();
+        payload.write_string(&pubkey.blob()?)?;
+        payload.write_string(data)?;
+        payload.write_uint32(flags)?;
+
+        let mut reader = self.request(SSH_AGENTC_SIGN_REQUEST, &payload)?;
+        if reader.read_u8()? != SSH_AGENT_SIGN_RESPONSE {
+            return Err(ErrorKind::AgentFailure.into());
+        }
+        Ok(reader.read_string()?)
+    }
+
+    fn request(&mut self, msgtype: u8, payload: &[u8]) -> OsshResult<Cursor<Vec<u8>>> {
+        let mut msg = Vec::with_capacity(payload.len() + 5);
+        msg.write_uint32(payload.len() as u32 + 1)?;
+        msg.write_u8(msgtype)?;
+        msg.write_all(payload)?;
+        self.stream.write_all(&msg)?;
+
+        let len = self.stream.read_uint32()? as usize;
+        if len == 0 || len > MAX_MESSAGE_LEN {
+            return Err(ErrorKind::InvalidLength.into());
+        }
+        let mut response = vec![0u8; len];
+        self.stream.read_exact(&mut response)?;
+        if response[0] == SSH_AGENT_FAILURE {
+            return Err(ErrorKind::AgentFailure.into());
+        }
+        Ok(Cursor::new(response))
+    }
+}
+
+/// A signer which holds the private key locally or in the SSH agent
+///
+/// Both variants produce the signature blob in the SSH wire format,
+/// so the caller doesn't need to care about where the private key is.
+pub enum Signer {
+    /// The private key is loaded in memory
+    Local(KeyPair),
+    /// The private key is held by the agent
+    Agent(Client, PublicKey),
+}
+
+impl Signer {
+    /// Get the public key of the signer
+    pub fn public_key(&self) -> OsshResult<PublicKey> {
+        match self {
+            Signer::Local(keypair) => keypair.clone_public_key(),
+            Signer::Agent(_, pubkey) => Ok(pubkey.clone()),
+        }
+    }
+
+    /// Sign the data, returning the signature blob in the SSH wire format
+    ///
+    /// For RSA keys, `alg` overrides the signature hash of the key.
+    /// See [`KeyPair::sign_with_alg()`](../keys/struct.KeyPair.html#method.sign_with_alg)
+    /// and [`Client::sign_with_alg()`](struct.Client.html#method.sign_with_alg).
+    pub fn sign(&mut self, data: &[u8], alg: Option<RsaSignature>) -> OsshResult<Vec<u8>> {
+        match self {
+            Signer::Local(keypair) => keypair.sign_with_alg(data, alg),
+            Signer::Agent(client, pubkey) => client.sign_with_alg(pubkey, data, alg),
+        }
+    }
+}
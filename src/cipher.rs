@@ -2,6 +2,13 @@ use std::str::FromStr;
 
 use self::internal_impl::*;
 use crate::error::{Error as OsshError, ErrorKind, OsshResult};
+use openssl::memcmp;
+use zeroize::Zeroizing;
+
+const CHACHA20_KEY_LEN: usize = 32;
+const POLY1305_KEY_LEN: usize = 32;
+const POLY1305_TAG_LEN: usize = 16;
+const GCM_TAG_LEN: usize = 16;
 
 /// Indicate the algorithm used by encryption/decryption
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -15,14 +22,38 @@ pub enum Cipher {
     Aes192_Ctr,
     Aes256_Ctr,
     TDes_Cbc,
+    Aes128_Gcm,
+    Aes256_Gcm,
+    Chacha20_Poly1305,
     Null,
 }
 
 impl Cipher {
+    /// All the ciphers which can encrypt the OpenSSH private keys, in the order of `ssh -Q cipher`
+    ///
+    /// `Cipher::Null` is not included, since it doesn't encrypt.
+    pub fn supported() -> &'static [Cipher] {
+        use Cipher::*;
+        &[
+            TDes_Cbc,
+            Aes128_Cbc,
+            Aes192_Cbc,
+            Aes256_Cbc,
+            Aes128_Ctr,
+            Aes192_Ctr,
+            Aes256_Ctr,
+            Aes128_Gcm,
+            Aes256_Gcm,
+            Chacha20_Poly1305,
+        ]
+    }
+
     /// Encrypt the data
     ///
     /// Mostly used by the internal codes.
     /// Usually you don't need to call it directly.
+    ///
+    /// The authentication tag of the AEAD ciphers is appended to the encrypted data.
     pub fn encrypt(self, src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<Vec<u8>> {
         let mut buf = vec![0; self.calc_buffer_len(src.len())];
         let n = self.encrypt_to(&mut buf, src, key, iv)?;
@@ -38,6 +69,7 @@ impl Cipher {
         iv: &[u8],
     ) -> OsshResult<usize> {
         use Cipher::*;
+        self.check_key_iv(key, iv)?;
         match self {
             Aes128_Cbc => aes128cbc_encrypt(dest, src, key, iv),
             Aes192_Cbc => aes192cbc_encrypt(dest, src, key, iv),
@@ -46,6 +78,8 @@ impl Cipher {
             Aes192_Ctr => aes192ctr_encrypt(dest, src, key, iv),
             Aes256_Ctr => aes256ctr_encrypt(dest, src, key, iv),
             TDes_Cbc => tdescbc_encrypt(dest, src, key, iv),
+            Aes128_Gcm | Aes256_Gcm => aesgcm_encrypt(dest, src, key, iv),
+            Chacha20_Poly1305 => chachapoly_crypt(dest, src, key, 0, 0, true),
             Null => {
                 if dest.len() >= src.len() {
                     dest[..src.len()].clone_from_slice(src);
@@ -61,6 +95,9 @@ impl Cipher {
     ///
     /// Mostly used by the internal codes.
     /// Usually you don't need to call it directly.
+    ///
+    /// The authentication tag of the AEAD ciphers must follow the encrypted data,
+    /// and `ErrorKind::IncorrectPass` is returned if the tag doesn't match.
     pub fn decrypt(self, src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<Vec<u8>> {
         let mut buf = vec![0; self.calc_buffer_len(src.len())];
         let n = self.decrypt_to(&mut buf, src, key, iv)?;
@@ -76,6 +113,7 @@ impl Cipher {
         iv: &[u8],
     ) -> OsshResult<usize> {
         use Cipher::*;
+        self.check_key_iv(key, iv)?;
         match self {
             Aes128_Cbc => aes128cbc_decrypt(dest, src, key, iv),
             Aes192_Cbc => aes192cbc_decrypt(dest, src, key, iv),
@@ -84,6 +122,8 @@ impl Cipher {
             Aes192_Ctr => aes192ctr_decrypt(dest, src, key, iv),
             Aes256_Ctr => aes256ctr_decrypt(dest, src, key, iv),
             TDes_Cbc => tdescbc_decrypt(dest, src, key, iv),
+            Aes128_Gcm | Aes256_Gcm => aesgcm_decrypt(dest, src, key, iv),
+            Chacha20_Poly1305 => chachapoly_crypt(dest, src, key, 0, 0, false),
             Null => {
                 if dest.len() >= src.len() {
                     dest[..src.len()].clone_from_slice(src);
@@ -95,6 +135,55 @@ impl Cipher {
         }
     }
 
+    /// Encrypt the data without padding
+    ///
+    /// The length of `src` must be a multiple of the block size.
+    /// The OpenSSH private key format pads the data by itself,
+    /// so the PKCS#7 padding of the CBC ciphers needs to be disabled.
+    pub(crate) fn encrypt_unpadded(self, src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<Vec<u8>> {
+        use Cipher::*;
+        match self {
+            Aes128_Cbc | Aes192_Cbc | Aes256_Cbc | TDes_Cbc => {
+                self.check_key_iv(key, iv)?;
+                let mut buf = vec![0; self.calc_buffer_len(src.len())];
+                let n = cbc_unpadded_encrypt(self, &mut buf, src, key, iv)?;
+                buf.truncate(n);
+                Ok(buf)
+            }
+            _ => self.encrypt(src, key, iv),
+        }
+    }
+
+    /// Decrypt the data without removing the padding
+    ///
+    /// See [encrypt_unpadded()](enum.Cipher.html#method.encrypt_unpadded).
+    pub(crate) fn decrypt_unpadded_to(
+        self,
+        dest: &mut [u8],
+        src: &[u8],
+        key: &[u8],
+        iv: &[u8],
+    ) -> OsshResult<usize> {
+        use Cipher::*;
+        match self {
+            Aes128_Cbc | Aes192_Cbc | Aes256_Cbc | TDes_Cbc => {
+                self.check_key_iv(key, iv)?;
+                cbc_unpadded_decrypt(self, dest, src, key, iv)
+            }
+            _ => self.decrypt_to(dest, src, key, iv),
+        }
+    }
+
+    // Check the key and IV lengths before passing them to the backend,
+    // otherwise a mis-sized IV may only be noticed as an incorrect passphrase
+    fn check_key_iv(self, key: &[u8], iv: &[u8]) -> OsshResult<()> {
+        if self.is_null() || (key.len() == self.key_len() && iv.len() == self.iv_len()) {
+            Ok(())
+        } else {
+            Err(ErrorKind::InvalidKeyIvLength.into())
+        }
+    }
+
     /// Get the required buffer length of the underlying backend
     ///
     /// Different cipher backend have different destination buffer length
@@ -114,7 +203,7 @@ impl Cipher {
     /// buf.truncate(n);
     /// ```
     pub fn calc_buffer_len(self, len: usize) -> usize {
-        calc_buflen(len, self.block_size())
+        calc_buflen(len, self.block_size()) + self.auth_len()
     }
 
     /// Return the required key length in bytes
@@ -128,6 +217,9 @@ impl Cipher {
             Aes192_Ctr => 24,
             Aes256_Ctr => 32,
             TDes_Cbc => 24,
+            Aes128_Gcm => 16,
+            Aes256_Gcm => 32,
+            Chacha20_Poly1305 => 64,
             Null => 0,
         }
     }
@@ -143,6 +235,9 @@ impl Cipher {
             Aes192_Ctr => 16,
             Aes256_Ctr => 16,
             TDes_Cbc => 8,
+            Aes128_Gcm => 12,
+            Aes256_Gcm => 12,
+            Chacha20_Poly1305 => 0,
             Null => 0,
         }
     }
@@ -158,10 +253,22 @@ impl Cipher {
             Aes192_Ctr => 16,
             Aes256_Ctr => 16,
             TDes_Cbc => 8,
+            Aes128_Gcm => 16,
+            Aes256_Gcm => 16,
+            Chacha20_Poly1305 => 8,
             Null => 8,
         }
     }
 
+    /// Return the length of the authentication tag, or 0 if the algorithm is not an AEAD cipher
+    pub fn auth_len(self) -> usize {
+        match self {
+            Cipher::Aes128_Gcm | Cipher::Aes256_Gcm => GCM_TAG_LEN,
+            Cipher::Chacha20_Poly1305 => POLY1305_TAG_LEN,
+            _ => 0,
+        }
+    }
+
     /// Return the name using in OpenSSH
     pub fn name(self) -> &'static str {
         use Cipher::*;
@@ -173,10 +280,20 @@ impl Cipher {
             Aes192_Ctr => "aes192-ctr",
             Aes256_Ctr => "aes256-ctr",
             TDes_Cbc => "3des-cbc",
+            Aes128_Gcm => "aes128-gcm@openssh.com",
+            Aes256_Gcm => "aes256-gcm@openssh.com",
+            Chacha20_Poly1305 => "chacha20-poly1305@openssh.com",
             Null => "none",
         }
     }
 
+    /// Return the name using in OpenSSH, which is accepted by [`from_str()`](#method.from_str)
+    ///
+    /// Same as [`name()`](#method.name).
+    pub fn openssh_name(self) -> &'static str {
+        self.name()
+    }
+
     /// Return `true` if `Cipher::Null`
     ///
     /// This is a method for check the null cipher easily
@@ -206,12 +323,68 @@ impl FromStr for Cipher {
             "aes128-ctr" => Ok(Aes128_Ctr),
             "aes192-ctr" => Ok(Aes192_Ctr),
             "aes256-ctr" => Ok(Aes256_Ctr),
+            "aes128-gcm@openssh.com" => Ok(Aes128_Gcm),
+            "aes256-gcm@openssh.com" => Ok(Aes256_Gcm),
+            "chacha20-poly1305@openssh.com" => Ok(Chacha20_Poly1305),
             "none" => Ok(Null),
             _ => Err(ErrorKind::UnsupportCipher.into()),
         }
     }
 }
 
+// The `chacha20-poly1305@openssh.com` AEAD cipher, see `PROTOCOL.chacha20poly1305` of OpenSSH
+//
+// The 64 bytes key holds two ChaCha20 keys, and both of them use the sequence number as the nonce.
+// The former one encrypts the payload from the block counter 1,
+// and its first keystream block is the Poly1305 key.
+// The latter one encrypts the first `aadlen` bytes, which is the packet length in the transport protocol.
+// The tag covers the whole ciphertext and follows it.
+// The private keys are encrypted with the sequence number 0 and without the additional data.
+fn chachapoly_crypt(
+    dest: &mut [u8],
+    src: &[u8],
+    key: &[u8],
+    seqnr: u64,
+    aadlen: usize,
+    encrypt: bool,
+) -> OsshResult<usize> {
+    let (main_key, header_key) = key.split_at(CHACHA20_KEY_LEN);
+    let len = if encrypt {
+        src.len()
+    } else {
+        src.len()
+            .checked_sub(POLY1305_TAG_LEN)
+            .ok_or(ErrorKind::InvalidLength)?
+    };
+    let outlen = if encrypt { len + POLY1305_TAG_LEN } else { len };
+    if len < aadlen || dest.len() < outlen {
+        return Err(ErrorKind::InvalidLength.into());
+    }
+
+    let mut poly_key = Zeroizing::new([0u8; POLY1305_KEY_LEN]);
+    chacha20_xor(&mut *poly_key, &[0u8; POLY1305_KEY_LEN], main_key, seqnr, 0)?;
+    if !encrypt {
+        let tag = poly1305(&*poly_key, &src[..len])?;
+        // Like the CBC padding, a wrong key can only be noticed here
+        if !memcmp::eq(&tag, &src[len..]) {
+            return Err(ErrorKind::IncorrectPass.into());
+        }
+    }
+    chacha20_xor(&mut dest[..aadlen], &src[..aadlen], header_key, seqnr, 0)?;
+    chacha20_xor(
+        &mut dest[aadlen..len],
+        &src[aadlen..len],
+        main_key,
+        seqnr,
+        1,
+    )?;
+    if encrypt {
+        let tag = poly1305(&*poly_key, &dest[..len])?;
+        dest[len..outlen].copy_from_slice(&tag);
+    }
+    Ok(outlen)
+}
+
 #[cfg(not(any(feature = "rustcrypto-cipher", feature = "openssl-cipher")))]
 compile_error!("No cipher backend is selected! Please enable one cipher backend feature.");
 
@@ -221,14 +394,20 @@ compile_error!("Multiple cipher backends are selected! Only one cipher backend i
 #[cfg(feature = "rustcrypto-cipher")]
 mod internal_impl {
     use aes::{Aes128, Aes192, Aes256};
+    use aes_gcm::{aead::AeadInPlace, Aes128Gcm, Aes256Gcm};
     use cbc::{Decryptor as CbcDecryptor, Encryptor as CbcEncryptor};
+    use chacha20::ChaCha20Legacy;
     use cipher::{
-        block_padding::Pkcs7, BlockCipher, BlockDecryptMut, BlockEncryptMut, KeyInit, KeyIvInit,
-        StreamCipher,
+        block_padding::{NoPadding, Padding, Pkcs7},
+        generic_array::GenericArray,
+        BlockCipher, BlockDecryptMut, BlockEncryptMut, KeyInit, KeyIvInit, StreamCipher,
+        StreamCipherSeek,
     };
     use ctr::Ctr128BE;
     use des::TdesEde3;
+    use poly1305::Poly1305;
 
+    use crate::cipher::{Cipher, GCM_TAG_LEN};
     use crate::error::{ErrorKind, OsshResult};
 
     type Aes128Ctr = Ctr128BE<Aes128>;
@@ -240,24 +419,57 @@ mod internal_impl {
         len + addi
     }
 
-    fn cbc_encrypt<C>(dest: &mut [u8], src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<usize>
+    fn cbc_encrypt<C, P>(dest: &mut [u8], src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<usize>
     where
         C: BlockCipher + BlockEncryptMut + KeyInit,
+        P: Padding<C::BlockSize>,
     {
         Ok(CbcEncryptor::<C>::new_from_slices(key, iv)?
-            .encrypt_padded_b2b_mut::<Pkcs7>(src, dest)?
+            .encrypt_padded_b2b_mut::<P>(src, dest)?
             .len())
     }
 
-    fn cbc_decrypt<C>(dest: &mut [u8], src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<usize>
+    fn cbc_decrypt<C, P>(dest: &mut [u8], src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<usize>
     where
         C: BlockCipher + BlockDecryptMut + KeyInit,
+        P: Padding<C::BlockSize>,
     {
         Ok(CbcDecryptor::<C>::new_from_slices(key, iv)?
-            .decrypt_padded_b2b_mut::<Pkcs7>(src, dest)?
+            .decrypt_padded_b2b_mut::<P>(src, dest)?
             .len())
     }
 
+    pub fn cbc_unpadded_encrypt(
+        cipher: Cipher,
+        dest: &mut [u8],
+        src: &[u8],
+        key: &[u8],
+        iv: &[u8],
+    ) -> OsshResult<usize> {
+        match cipher {
+            Cipher::Aes128_Cbc => cbc_encrypt::<Aes128, NoPadding>(dest, src, key, iv),
+            Cipher::Aes192_Cbc => cbc_encrypt::<Aes192, NoPadding>(dest, src, key, iv),
+            Cipher::Aes256_Cbc => cbc_encrypt::<Aes256, NoPadding>(dest, src, key, iv),
+            Cipher::TDes_Cbc => cbc_encrypt::<TdesEde3, NoPadding>(dest, src, key, iv),
+            _ => Err(ErrorKind::UnsupportCipher.into()),
+        }
+    }
+    pub fn cbc_unpadded_decrypt(
+        cipher: Cipher,
+        dest: &mut [u8],
+        src: &[u8],
+        key: &[u8],
+        iv: &[u8],
+    ) -> OsshResult<usize> {
+        match cipher {
+            Cipher::Aes128_Cbc => cbc_decrypt::<Aes128, NoPadding>(dest, src, key, iv),
+            Cipher::Aes192_Cbc => cbc_decrypt::<Aes192, NoPadding>(dest, src, key, iv),
+            Cipher::Aes256_Cbc => cbc_decrypt::<Aes256, NoPadding>(dest, src, key, iv),
+            Cipher::TDes_Cbc => cbc_decrypt::<TdesEde3, NoPadding>(dest, src, key, iv),
+            _ => Err(ErrorKind::UnsupportCipher.into()),
+        }
+    }
+
     fn ctr_endecrypt<C>(dest: &mut [u8], src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<usize>
     where
         C: StreamCipher + KeyIvInit,
@@ -276,7 +488,7 @@ mod internal_impl {
         key: &[u8],
         iv: &[u8],
     ) -> OsshResult<usize> {
-        cbc_encrypt::<Aes128>(dest, src, key, iv)
+        cbc_encrypt::<Aes128, Pkcs7>(dest, src, key, iv)
     }
     pub fn aes128cbc_decrypt(
         dest: &mut [u8],
@@ -284,7 +496,7 @@ mod internal_impl {
         key: &[u8],
         iv: &[u8],
     ) -> OsshResult<usize> {
-        cbc_decrypt::<Aes128>(dest, src, key, iv)
+        cbc_decrypt::<Aes128, Pkcs7>(dest, src, key, iv)
     }
 
     pub fn aes192cbc_encrypt(
@@ -293,7 +505,7 @@ mod internal_impl {
         key: &[u8],
         iv: &[u8],
     ) -> OsshResult<usize> {
-        cbc_encrypt::<Aes192>(dest, src, key, iv)
+        cbc_encrypt::<Aes192, Pkcs7>(dest, src, key, iv)
     }
     pub fn aes192cbc_decrypt(
         dest: &mut [u8],
@@ -301,7 +513,7 @@ mod internal_impl {
         key: &[u8],
         iv: &[u8],
     ) -> OsshResult<usize> {
-        cbc_decrypt::<Aes192>(dest, src, key, iv)
+        cbc_decrypt::<Aes192, Pkcs7>(dest, src, key, iv)
     }
 
     pub fn aes256cbc_encrypt(
@@ -310,7 +522,7 @@ mod internal_impl {
         key: &[u8],
         iv: &[u8],
     ) -> OsshResult<usize> {
-        cbc_encrypt::<Aes256>(dest, src, key, iv)
+        cbc_encrypt::<Aes256, Pkcs7>(dest, src, key, iv)
     }
     pub fn aes256cbc_decrypt(
         dest: &mut [u8],
@@ -318,7 +530,7 @@ mod internal_impl {
         key: &[u8],
         iv: &[u8],
     ) -> OsshResult<usize> {
-        cbc_decrypt::<Aes256>(dest, src, key, iv)
+        cbc_decrypt::<Aes256, Pkcs7>(dest, src, key, iv)
     }
 
     pub fn tdescbc_encrypt(
@@ -327,7 +539,7 @@ mod internal_impl {
         key: &[u8],
         iv: &[u8],
     ) -> OsshResult<usize> {
-        cbc_encrypt::<TdesEde3>(dest, src, key, iv)
+        cbc_encrypt::<TdesEde3, Pkcs7>(dest, src, key, iv)
     }
     pub fn tdescbc_decrypt(
         dest: &mut [u8],
@@ -335,7 +547,7 @@ mod internal_impl {
         key: &[u8],
         iv: &[u8],
     ) -> OsshResult<usize> {
-        cbc_decrypt::<TdesEde3>(dest, src, key, iv)
+        cbc_decrypt::<TdesEde3, Pkcs7>(dest, src, key, iv)
     }
 
     pub fn aes128ctr_encrypt(
@@ -388,13 +600,94 @@ mod internal_impl {
     ) -> OsshResult<usize> {
         ctr_endecrypt::<Aes256Ctr>(dest, src, key, iv)
     }
+
+    // AES-GCM with the 96 bits IV, there is never additional data in the private keys
+    fn gcm_crypt<C>(
+        dest: &mut [u8],
+        src: &[u8],
+        key: &[u8],
+        iv: &[u8],
+        encrypt: bool,
+    ) -> OsshResult<usize>
+    where
+        C: AeadInPlace + KeyInit,
+    {
+        let cipher = C::new_from_slice(key)?;
+        let nonce = GenericArray::from_exact_iter(iv.iter().copied())
+            .ok_or(ErrorKind::InvalidKeyIvLength)?;
+
+        let len = if encrypt {
+            src.len()
+        } else {
+            src.len()
+                .checked_sub(GCM_TAG_LEN)
+                .ok_or(ErrorKind::InvalidLength)?
+        };
+        let outlen = if encrypt { len + GCM_TAG_LEN } else { len };
+        if dest.len() < outlen {
+            return Err(ErrorKind::InvalidLength.into());
+        }
+        dest[..len].copy_from_slice(&src[..len]);
+        if encrypt {
+            let tag = cipher
+                .encrypt_in_place_detached(&nonce, &[], &mut dest[..len])
+                .map_err(|_| ErrorKind::InvalidLength)?;
+            dest[len..outlen].copy_from_slice(&tag);
+        } else {
+            let tag = GenericArray::from_slice(&src[len..]);
+            // Like the CBC padding, a wrong key can only be noticed here
+            cipher
+                .decrypt_in_place_detached(&nonce, &[], &mut dest[..len], tag)
+                .map_err(|_| ErrorKind::IncorrectPass)?;
+        }
+        Ok(outlen)
+    }
+
+    pub fn aesgcm_encrypt(dest: &mut [u8], src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<usize> {
+        match key.len() {
+            16 => gcm_crypt::<Aes128Gcm>(dest, src, key, iv, true),
+            32 => gcm_crypt::<Aes256Gcm>(dest, src, key, iv, true),
+            _ => Err(ErrorKind::InvalidKeyIvLength.into()),
+        }
+    }
+    pub fn aesgcm_decrypt(dest: &mut [u8], src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<usize> {
+        match key.len() {
+            16 => gcm_crypt::<Aes128Gcm>(dest, src, key, iv, false),
+            32 => gcm_crypt::<Aes256Gcm>(dest, src, key, iv, false),
+            _ => Err(ErrorKind::InvalidKeyIvLength.into()),
+        }
+    }
+
+    // The original ChaCha20 with the 64 bits nonce, as used by OpenSSH
+    pub fn chacha20_xor(
+        dest: &mut [u8],
+        src: &[u8],
+        key: &[u8],
+        nonce: u64,
+        counter: u64,
+    ) -> OsshResult<()> {
+        if dest.len() < src.len() {
+            return Err(ErrorKind::InvalidLength.into());
+        }
+        let mut cipher = ChaCha20Legacy::new_from_slices(key, &nonce.to_be_bytes())?;
+        cipher.try_seek(counter * 64)?;
+        cipher.apply_keystream_b2b(src, &mut dest[..src.len()])?;
+        Ok(())
+    }
+
+    pub fn poly1305(key: &[u8], data: &[u8]) -> OsshResult<[u8; 16]> {
+        Ok(Poly1305::new_from_slice(key)?.compute_unpadded(data).into())
+    }
 }
 
 #[cfg(feature = "openssl-cipher")]
 mod internal_impl {
+    use openssl::pkey::{Id, PKey};
+    use openssl::sign::Signer;
     use openssl::symm::{Cipher, Crypter, Mode};
 
-    use crate::error::OsshResult;
+    use crate::cipher::GCM_TAG_LEN;
+    use crate::error::{ErrorKind, OsshResult};
 
     fn openssl_encrypt(
         cipher: Cipher,
@@ -422,6 +715,51 @@ mod internal_impl {
         Ok(n)
     }
 
+    fn openssl_cbc_cipher(cipher: crate::cipher::Cipher) -> OsshResult<Cipher> {
+        use crate::cipher::Cipher::*;
+        match cipher {
+            Aes128_Cbc => Ok(Cipher::aes_128_cbc()),
+            Aes192_Cbc => Ok(Cipher::aes_192_cbc()),
+            Aes256_Cbc => Ok(Cipher::aes_256_cbc()),
+            TDes_Cbc => Ok(Cipher::des_ede3_cbc()),
+            _ => Err(ErrorKind::UnsupportCipher.into()),
+        }
+    }
+
+    fn openssl_unpadded(
+        cipher: crate::cipher::Cipher,
+        mode: Mode,
+        dest: &mut [u8],
+        src: &[u8],
+        key: &[u8],
+        iv: &[u8],
+    ) -> OsshResult<usize> {
+        let mut crypt = Crypter::new(openssl_cbc_cipher(cipher)?, mode, key, Some(iv))?;
+        crypt.pad(false);
+        let mut n = crypt.update(src, dest)?;
+        n += crypt.finalize(&mut dest[n..])?;
+        Ok(n)
+    }
+
+    pub fn cbc_unpadded_encrypt(
+        cipher: crate::cipher::Cipher,
+        dest: &mut [u8],
+        src: &[u8],
+        key: &[u8],
+        iv: &[u8],
+    ) -> OsshResult<usize> {
+        openssl_unpadded(cipher, Mode::Encrypt, dest, src, key, iv)
+    }
+    pub fn cbc_unpadded_decrypt(
+        cipher: crate::cipher::Cipher,
+        dest: &mut [u8],
+        src: &[u8],
+        key: &[u8],
+        iv: &[u8],
+    ) -> OsshResult<usize> {
+        openssl_unpadded(cipher, Mode::Decrypt, dest, src, key, iv)
+    }
+
     pub fn calc_buflen(len: usize, block_size: usize) -> usize {
         let bs = block_size;
         len + bs
@@ -545,4 +883,207 @@ mod internal_impl {
     ) -> OsshResult<usize> {
         openssl_decrypt(Cipher::aes_256_ctr(), dest, src, key, iv)
     }
+
+    fn openssl_gcm_cipher(key: &[u8]) -> OsshResult<Cipher> {
+        match key.len() {
+            16 => Ok(Cipher::aes_128_gcm()),
+            32 => Ok(Cipher::aes_256_gcm()),
+            _ => Err(ErrorKind::InvalidKeyIvLength.into()),
+        }
+    }
+
+    pub fn aesgcm_encrypt(dest: &mut [u8], src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<usize> {
+        let mut crypt = Crypter::new(openssl_gcm_cipher(key)?, Mode::Encrypt, key, Some(iv))?;
+        let mut n = crypt.update(src, dest)?;
+        n += crypt.finalize(&mut dest[n..])?;
+        let tag = dest
+            .get_mut(n..n + GCM_TAG_LEN)
+            .ok_or(ErrorKind::InvalidLength)?;
+        crypt.get_tag(tag)?;
+        Ok(n + GCM_TAG_LEN)
+    }
+    pub fn aesgcm_decrypt(dest: &mut [u8], src: &[u8], key: &[u8], iv: &[u8]) -> OsshResult<usize> {
+        let len = src
+            .len()
+            .checked_sub(GCM_TAG_LEN)
+            .ok_or(ErrorKind::InvalidLength)?;
+        let mut crypt = Crypter::new(openssl_gcm_cipher(key)?, Mode::Decrypt, key, Some(iv))?;
+        crypt.set_tag(&src[len..])?;
+        let n = crypt.update(&src[..len], dest)?;
+        // Like the CBC padding, a wrong key can only be noticed here
+        match crypt.finalize(&mut dest[n..]) {
+            Ok(m) => Ok(n + m),
+            Err(_) => Err(ErrorKind::IncorrectPass.into()),
+        }
+    }
+
+    // The original ChaCha20 with the 64 bits counter and the 64 bits nonce, as used by OpenSSH
+    pub fn chacha20_xor(
+        dest: &mut [u8],
+        src: &[u8],
+        key: &[u8],
+        nonce: u64,
+        counter: u64,
+    ) -> OsshResult<()> {
+        if dest.len() < src.len() {
+            return Err(ErrorKind::InvalidLength.into());
+        }
+        // OpenSSL takes the counter words and the nonce words in a single 16 bytes IV
+        let mut iv = [0u8; 16];
+        iv[..8].copy_from_slice(&counter.to_le_bytes());
+        iv[8..].copy_from_slice(&nonce.to_be_bytes());
+        let mut crypt = Crypter::new(Cipher::chacha20(), Mode::Encrypt, key, Some(&iv))?;
+        // The stream cipher never buffers, but OpenSSL asks for a larger output buffer
+        let mut buf = zeroize::Zeroizing::new(vec![0u8; src.len() + 1]);
+        let n = crypt.update(src, &mut buf)?;
+        dest[..n].copy_from_slice(&buf[..n]);
+        Ok(())
+    }
+
+    pub fn poly1305(key: &[u8], data: &[u8]) -> OsshResult<[u8; 16]> {
+        let pkey = PKey::private_key_from_raw_bytes(key, Id::POLY1305)?;
+        let mut signer = Signer::new_without_digest(&pkey)?;
+        signer.update(data)?;
+        let mut tag = [0u8; 16];
+        signer.sign(&mut tag)?;
+        Ok(tag)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn cipher_key_iv_length() {
+        let ciphers = [
+            Cipher::Aes128_Cbc,
+            Cipher::Aes192_Cbc,
+            Cipher::Aes256_Cbc,
+            Cipher::Aes128_Ctr,
+            Cipher::Aes192_Ctr,
+            Cipher::Aes256_Ctr,
+            Cipher::TDes_Cbc,
+            Cipher::Aes128_Gcm,
+            Cipher::Aes256_Gcm,
+        ];
+        let data = [0x55u8; 32];
+        for cipher in ciphers {
+            let key = vec![1u8; cipher.key_len()];
+            let iv = vec![2u8; cipher.iv_len()];
+            let encrypted = cipher.encrypt_unpadded(&data, &key, &iv).unwrap();
+            let mut buf = vec![0u8; cipher.calc_buffer_len(encrypted.len())];
+            let n = cipher
+                .decrypt_unpadded_to(&mut buf, &encrypted, &key, &iv)
+                .unwrap();
+            assert_eq!(&buf[..n], &data[..]);
+
+            for (key, iv) in [(&key[1..], &iv[..]), (&key[..], &iv[1..])] {
+                assert_eq!(
+                    cipher.encrypt(&data, key, iv).unwrap_err().kind(),
+                    ErrorKind::InvalidKeyIvLength
+                );
+                assert_eq!(
+                    cipher
+                        .decrypt_unpadded_to(&mut buf, &encrypted, key, iv)
+                        .unwrap_err()
+                        .kind(),
+                    ErrorKind::InvalidKeyIvLength
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn chacha20_poly1305_vectors() {
+        // RFC 8439 section 2.5.2
+        assert_eq!(
+            poly1305(
+                &hex!("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b"),
+                b"Cryptographic Forum Research Group"
+            )
+            .unwrap(),
+            hex!("a8061dc1305136c6c22b8baf0c0127a9")
+        );
+
+        let key: Vec<u8> = (0..64).collect();
+        // A transport packet with the encrypted length field
+        let packet = hex!(
+            "0000002554686520717569636b2062726f776e20666f78206a756d7073206f7665722074686520646f"
+        );
+        let sealed = hex!("a39afc8f7c2e70633ff6433d074dd982b8f8bd0c52dd4816149650f3334a74a9e5212320cc32d1c17aa25d204f28ce5b7a970113b46f80f1ab");
+        let mut buf = [0u8; 57];
+        assert_eq!(
+            chachapoly_crypt(&mut buf, &packet, &key, 7, 4, true).unwrap(),
+            sealed.len()
+        );
+        assert_eq!(buf, sealed);
+        assert_eq!(
+            chachapoly_crypt(&mut buf, &sealed, &key, 7, 4, false).unwrap(),
+            packet.len()
+        );
+        assert_eq!(&buf[..packet.len()], &packet[..]);
+
+        // The private key section, without the additional data
+        let cipher = Cipher::Chacha20_Poly1305;
+        let data: Vec<u8> = (0..70).collect();
+        let encrypted = cipher.encrypt(&data, &key, &[]).unwrap();
+        assert_eq!(
+            encrypted,
+            hex!("18b94032a9e3a0d61b68566aa34e4028e8a0e1e6f5b84d4bf4e1e60936286b4352292a4ef5cb1a7ab5a8720f48232e13ab91c46dea686ff9351370440da864f202b36f9fe30f2aec8b855447c1a78a1981c90cb8d130")
+        );
+        assert_eq!(cipher.decrypt(&encrypted, &key, &[]).unwrap(), data);
+
+        let mut tampered = encrypted;
+        tampered[0] ^= 1;
+        assert_eq!(
+            cipher.decrypt(&tampered, &key, &[]).unwrap_err().kind(),
+            ErrorKind::IncorrectPass
+        );
+    }
+
+    #[test]
+    fn aes_gcm_vectors() {
+        let key: Vec<u8> = (0..32).collect();
+        let iv: Vec<u8> = (100..112).collect();
+        let data: Vec<u8> = (0..70).collect();
+        for (cipher, expected) in [
+            (
+                Cipher::Aes128_Gcm,
+                &hex!("1a634ca6df07befd93375d1b8ac725e320996aec86bccb4dec76ab8ca0efe9ec9add7007af3d01b6a2710d6da1af6a28ebac2f40a933771ffbb73b66f0a35b5f24db504ee8f2d04d47c0dda7f32bd9a9d29ae3cc98b3")[..],
+            ),
+            (
+                Cipher::Aes256_Gcm,
+                &hex!("481adc657dec5099366b55e3d66864f252d314199f79e565bfc8b653e7bebb57b4c862e36837bd23d5da5214d16fe4e02b6aaad4147f771fc2c896edd3bc9bb548cd994d920e9928e0cb5b610d132879263c1227e8c9")[..],
+            ),
+        ] {
+            let key = &key[..cipher.key_len()];
+            let encrypted = cipher.encrypt(&data, key, &iv).unwrap();
+            assert_eq!(encrypted, expected);
+            assert_eq!(cipher.decrypt(&encrypted, key, &iv).unwrap(), data);
+
+            let mut tampered = encrypted;
+            *tampered.last_mut().unwrap() ^= 1;
+            assert_eq!(
+                cipher.decrypt(&tampered, key, &iv).unwrap_err().kind(),
+                ErrorKind::IncorrectPass
+            );
+        }
+    }
+
+    #[test]
+    fn cipher_supported() {
+        let ciphers = Cipher::supported();
+        assert!(!ciphers.contains(&Cipher::Null));
+        let data = [0x55u8; 32];
+        for &cipher in ciphers {
+            assert_eq!(Cipher::from_str(cipher.openssh_name()).unwrap(), cipher);
+            let key = vec![1u8; cipher.key_len()];
+            let iv = vec![2u8; cipher.iv_len()];
+            let encrypted = cipher.encrypt_unpadded(&data, &key, &iv).unwrap();
+            assert_eq!(encrypted.len(), data.len() + cipher.auth_len());
+            assert_eq!(data.len() % cipher.block_size(), 0);
+        }
+    }
 }
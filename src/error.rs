@@ -31,11 +31,30 @@ impl Error {
         }
     }
 
+    #[inline]
+    pub(crate) fn with_failure<S: Into<String>>(kind: ErrorKind, name: S) -> Self {
+        Self::with_error(
+            kind,
+            Failure {
+                kind,
+                name: name.into(),
+            },
+        )
+    }
+
     /// Get the kind of the error
     pub fn kind(&self) -> ErrorKind {
         self.kind
     }
 
+    /// Get the name causing the error, e.g. the key type name of an [`ErrorKind::UnsupportType`](enum.ErrorKind.html#variant.UnsupportType) error
+    pub fn failure(&self) -> Option<&str> {
+        self.inner
+            .as_ref()
+            .and_then(|e| e.downcast_ref::<Failure>())
+            .map(|f| f.name.as_str())
+    }
+
     pub fn backtrace(&self) -> &Backtrace {
         &self.bt
     }
@@ -157,6 +176,28 @@ impl From<std::array::TryFromSliceError> for Error {
     }
 }
 
+// The name which is unknown or unsupported by this crate
+#[derive(Debug)]
+struct Failure {
+    kind: ErrorKind,
+    name: String,
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.kind {
+            ErrorKind::UnsupportType => write!(
+                f,
+                "`{}` is not supported by this version of osshkeys, updating the crate may add the support",
+                self.name
+            ),
+            _ => write!(f, "`{}`", self.name),
+        }
+    }
+}
+
+impl StdError for Failure {}
+
 /// Indicate the reason of the error
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ErrorKind {
@@ -188,6 +229,8 @@ pub enum ErrorKind {
     UnsupportCipher,
     /// The passphrase is incorrect, can't decrypt the key
     IncorrectPass,
+    /// The PEM or PKCS#8 key is encrypted, but no passphrase is given
+    PassphraseRequired,
     /// The key type is not the desired one
     TypeNotMatch,
     /// The key type is not supported
@@ -196,6 +239,24 @@ pub enum ErrorKind {
     InvalidPemFormat,
     /// The key or IV length can't meet the cipher's requirement
     InvalidKeyIvLength,
+    /// The signature algorithm is rejected by the verification policy
+    WeakSignature,
+    /// The SSH agent refused the request or replied an unexpected message
+    AgentFailure,
+    /// The operation is cancelled by the callback
+    Cancelled,
+    /// The private key is stored in a security key (e.g. a FIDO token), so it must be used on the token
+    SecurityKeyRequired,
+    /// The namespace of the SSHSIG signature is not the expected one
+    NamespaceMismatch,
+    /// The certificate can't be used, e.g. it's expired or not issued to the principal
+    CertificateRejected,
+    /// The private key and the public key don't correspond
+    KeyMismatch,
+    /// The key is larger than the limit of the parser
+    KeyTooLarge,
+    /// There are extra bytes after the data
+    TrailingData,
     /// Something shouldn't happen but it DID happen...
     Unknown,
 }
@@ -220,10 +281,20 @@ impl ErrorKind {
             UnsupportCurve => "Unsupported Elliptic Curve",
             UnsupportCipher => "Unsupported Cipher",
             IncorrectPass => "Incorrect Passphrase",
+            PassphraseRequired => "Passphrase Required",
             TypeNotMatch => "Key Type Not Match",
             UnsupportType => "Unsupported Key Type",
             InvalidPemFormat => "Invalid PEM Format",
             InvalidKeyIvLength => "Invalid Key/IV Length",
+            WeakSignature => "Weak Signature Algorithm",
+            AgentFailure => "SSH Agent Failure",
+            Cancelled => "Operation Cancelled",
+            SecurityKeyRequired => "Security Key Required",
+            NamespaceMismatch => "Signature Namespace Mismatch",
+            CertificateRejected => "Certificate Rejected",
+            KeyMismatch => "Key Pair Mismatch",
+            KeyTooLarge => "Key Too Large",
+            TrailingData => "Trailing Data",
             Unknown => "Unknown Error",
         }
     }
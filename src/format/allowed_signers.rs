@@ -0,0 +1,158 @@
+use super::authorized_keys::{split_field, split_options};
+use crate::keys::{PublicKey, PublicParts};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A signer entry of the allowed_signers file
+struct Entry {
+    principals: String,
+    cert_authority: bool,
+    valid_after: Option<u64>,
+    valid_before: Option<u64>,
+    key: PublicKey,
+}
+
+impl Entry {
+    fn is_valid_at(&self, timestamp: u64) -> bool {
+        self.valid_after.map_or(true, |after| timestamp >= after)
+            && self.valid_before.map_or(true, |before| timestamp <= before)
+    }
+}
+
+/// Find the principals allowed to sign with the key in an `allowed_signers` file
+///
+/// This is the lookup done by `ssh-keygen -Y find-principals`,
+/// see the ALLOWED SIGNERS section of ssh-keygen(1) for the file format.
+/// The principals of the first entry matching the key are returned,
+/// with the comma separated list split, like `ssh-keygen` does.
+///
+/// The entries outside their `valid-after`/`valid-before` period at the given time are skipped,
+/// as are the `cert-authority` entries, since they match certificates instead of plain keys.
+/// The times are always interpreted as UTC, whether or not they are suffixed with `Z`.
+/// The lines which can't be parsed, e.g. with an unknown option, are ignored.
+pub fn find_principals(allowed_signers: &str, pubkey: &PublicKey, at: SystemTime) -> Vec<String> {
+    let blob = match pubkey.blob() {
+        Ok(blob) => blob,
+        Err(_) => return Vec::new(),
+    };
+    let at = at.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    allowed_signers
+        .lines()
+        .filter_map(parse_entry)
+        .find(|entry| {
+            !entry.cert_authority
+                && entry.is_valid_at(at)
+                && entry.key.blob().map_or(false, |b| b == blob)
+        })
+        .map_or_else(Vec::new, |entry| {
+            entry
+                .principals
+                .split(',')
+                .filter(|p| !p.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+}
+
+fn parse_entry(raw: &str) -> Option<Entry> {
+    let line = raw.trim_matches(|c: char| c.is_ascii_whitespace());
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (principals, rest, _) = split_field(line)?;
+    let (first, rest, _) = split_field(rest)?;
+    let (second, rest, _) = split_field(rest)?;
+    // The options are absent if the principals are followed by a key type and a valid key
+    let (options, key) = match parse_key(first, second) {
+        Some(key) => (None, key),
+        None => {
+            let (third, _, _) = split_field(rest)?;
+            (Some(first), parse_key(second, third)?)
+        }
+    };
+
+    let mut entry = Entry {
+        principals: unquote(principals).to_owned(),
+        cert_authority: false,
+        valid_after: None,
+        valid_before: None,
+        key,
+    };
+    for option in options.map(split_options).unwrap_or(Some(Vec::new()))? {
+        let (name, value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(unquote(value))),
+            None => (option, None),
+        };
+        match (name.to_ascii_lowercase().as_str(), value) {
+            ("cert-authority", None) => entry.cert_authority = true,
+            ("namespaces", Some(_)) => {}
+            ("valid-after", Some(value)) => entry.valid_after = Some(parse_time(value)?),
+            ("valid-before", Some(value)) => entry.valid_before = Some(parse_time(value)?),
+            _ => return None,
+        }
+    }
+    Some(entry)
+}
+
+fn parse_key(keytype: &str, keyb64: &str) -> Option<PublicKey> {
+    if keyb64.is_empty() {
+        return None;
+    }
+    PublicKey::from_keystr(&format!("{} {}", keytype, keyb64)).ok()
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+// Parse the `YYYYMMDD[Z]` or `YYYYMMDDHHMM[SS][Z]` time to the UNIX timestamp
+fn parse_time(s: &str) -> Option<u64> {
+    let s = s.strip_suffix(['Z', 'z']).unwrap_or(s);
+    if !matches!(s.len(), 8 | 12 | 14) || !s.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| s.get(range).map_or(0, |f| f.parse().unwrap());
+    let (year, month, day) = (field(0..4), field(4..6), field(6..8));
+    let (hour, minute, second) = (field(8..10), field(10..12), field(12..14));
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    if days < 0 {
+        return None;
+    }
+    Some(days as u64 * 86400 + hour as u64 * 3600 + minute as u64 * 60 + second as u64)
+}
+
+// The days since 1970-01-01 of the proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn allowed_signers_time() {
+        assert_eq!(parse_time("19700101"), Some(0));
+        assert_eq!(parse_time("20240229Z"), Some(1709164800));
+        assert_eq!(parse_time("202402291230"), Some(1709209800));
+        assert_eq!(parse_time("20240229123045Z"), Some(1709209845));
+        assert_eq!(parse_time("2024022"), None);
+        assert_eq!(parse_time("20241301"), None);
+        assert_eq!(parse_time("19691231"), None);
+        assert_eq!(parse_time("2024-02-29"), None);
+    }
+}
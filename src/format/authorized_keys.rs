@@ -0,0 +1,413 @@
+use super::ossh_pubkey::serialize_ossh_pubkey;
+use super::sanitize_comment;
+use crate::error::*;
+use crate::keys::{Key, KeyType, PublicKey, PublicParts};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::BufRead;
+
+// The RSA keys smaller than this are reported as weak
+const MIN_RSA_BITS: usize = 2048;
+
+// The options understood by sshd, see the AUTHORIZED_KEYS FILE FORMAT section of sshd(8)
+const KNOWN_OPTIONS: &[&str] = &[
+    "agent-forwarding",
+    "cert-authority",
+    "command",
+    "environment",
+    "expiry-time",
+    "from",
+    "no-agent-forwarding",
+    "no-port-forwarding",
+    "no-pty",
+    "no-touch-required",
+    "no-user-rc",
+    "no-x11-forwarding",
+    "permitlisten",
+    "permitopen",
+    "port-forwarding",
+    "principals",
+    "pty",
+    "restrict",
+    "tunnel",
+    "user-rc",
+    "verify-required",
+    "x11-forwarding",
+];
+
+/// The category of an [`AuthKeysIssue`](struct.AuthKeysIssue.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthKeysIssueKind {
+    /// The line can't be parsed as a key entry
+    Unparseable,
+    /// The key already appears on the given line
+    Duplicate(usize),
+    /// The key is too small to be considered secure
+    Weak,
+    /// The key uses a deprecated algorithm, e.g. `ssh-dss`
+    DeprecatedAlgorithm,
+    /// The option is unknown or grants unrestricted access
+    SuspiciousOption(String),
+    /// The fields are not separated by exactly one space, or there is leading or trailing whitespace
+    Whitespace,
+}
+
+/// An issue found by [`lint_authorized_keys()`](fn.lint_authorized_keys.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthKeysIssue {
+    /// The line number, starting from 1
+    pub line: usize,
+    /// The category of the issue
+    pub kind: AuthKeysIssueKind,
+}
+
+/// A key entry of an `authorized_keys` file, parsed by [`parse_authorized_keys()`](fn.parse_authorized_keys.html)
+#[derive(Clone)]
+pub struct AuthorizedKey {
+    /// The options prefix as written in the file, e.g. `no-pty,command="..."`
+    pub options: Option<String>,
+    /// The public key, with the trailing comment as its comment
+    pub key: PublicKey,
+    /// The trailing comment
+    pub comment: Option<String>,
+}
+
+impl AuthorizedKey {
+    /// Append an option to the options prefix, e.g. `restrict` or `from="10.0.0.0/8,192.168.0.0/16"`
+    ///
+    /// The value is always quoted, as required by sshd even without a comma or whitespace,
+    /// and the double quotes in it are escaped as `\"`.
+    ///
+    /// sshd treats only `\"` as an escape, so a value ending with a backslash can't be written
+    /// and returns [`ErrorKind::InvalidArgument`](../../error/enum.ErrorKind.html#variant.InvalidArgument).
+    pub fn add_option(&mut self, name: &str, value: Option<&str>) -> OsshResult<()> {
+        let mut option = name.to_owned();
+        if let Some(value) = value {
+            if value.ends_with('\\') {
+                return Err(Error::with_failure(
+                    ErrorKind::InvalidArgument,
+                    "option value ends with a backslash",
+                ));
+            }
+            option.push_str("=\"");
+            option.push_str(&value.replace('"', "\\\""));
+            option.push('"');
+        }
+        match &mut self.options {
+            Some(options) if !options.is_empty() => {
+                options.push(',');
+                options.push_str(&option);
+            }
+            options => *options = Some(option),
+        }
+        Ok(())
+    }
+}
+
+/// Format as a line of `authorized_keys` with the canonical whitespace, without the trailing newline
+///
+/// The options prefix is written as is, so the quoting of the parsed options is kept.
+/// The comment is taken from the `comment` field rather than the key.
+/// Like [`PublicKey`](../../keys/struct.PublicKey.html), the control characters are removed from the options and the comment,
+/// since a line break would inject another entry.
+impl fmt::Display for AuthorizedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Only the ASCII control characters are removed, so it is still valid UTF-8
+        let sanitized = |s: &str| String::from_utf8(sanitize_comment(s.as_bytes())).unwrap();
+        if let Some(options) = self.options.as_deref().filter(|o| !o.is_empty()) {
+            write!(f, "{} ", sanitized(options))?;
+        }
+        f.write_str(&serialize_ossh_pubkey(&self.key, "").unwrap())?;
+        if let Some(comment) = self.comment.as_deref().filter(|c| !c.is_empty()) {
+            write!(f, " {}", sanitized(comment))?;
+        }
+        Ok(())
+    }
+}
+
+// A key entry split into its fields
+struct Entry<'a> {
+    options: Option<&'a str>,
+    keytype: &'a str,
+    keyb64: &'a str,
+    comment: Option<&'a str>,
+    canonical: bool,
+}
+
+impl Entry<'_> {
+    fn parse_key(&self) -> Option<PublicKey> {
+        PublicKey::from_keystr(&format!("{} {}", self.keytype, self.keyb64)).ok()
+    }
+
+    fn to_canonical(&self) -> String {
+        let mut line = String::new();
+        if let Some(options) = self.options {
+            line.push_str(options);
+            line.push(' ');
+        }
+        line.push_str(self.keytype);
+        line.push(' ');
+        line.push_str(self.keyb64);
+        if let Some(comment) = self.comment {
+            line.push(' ');
+            line.push_str(comment);
+        }
+        line
+    }
+}
+
+/// Check an `authorized_keys` file and report the issues of every line
+///
+/// The empty lines and the lines starting with `#` are skipped.
+/// The issues of each line are reported in the order of the line number.
+///
+/// The following issues are detected:
+/// - The lines which can't be parsed
+/// - The keys appearing more than once
+/// - The RSA keys smaller than 2048 bits
+/// - The DSA keys, which are deprecated by OpenSSH
+/// - The unknown options, and `from` or `permitopen`/`permitlisten` allowing everything
+/// - The non-canonical whitespace between the fields
+pub fn lint_authorized_keys(input: &str) -> Vec<AuthKeysIssue> {
+    let mut issues = Vec::new();
+    let mut seen: HashMap<Vec<u8>, usize> = HashMap::new();
+    for (idx, raw) in input.lines().enumerate() {
+        let line = idx + 1;
+        let mut report = |kind| issues.push(AuthKeysIssue { line, kind });
+        if is_skipped(raw) {
+            continue;
+        }
+        let (entry, key) = match split_entry(raw).and_then(|e| e.parse_key().map(|k| (e, k))) {
+            Some(parsed) => parsed,
+            None => {
+                report(AuthKeysIssueKind::Unparseable);
+                continue;
+            }
+        };
+
+        if let Ok(blob) = key.blob() {
+            if let Some(&first) = seen.get(&blob) {
+                report(AuthKeysIssueKind::Duplicate(first));
+            } else {
+                seen.insert(blob, line);
+            }
+        }
+        match key.keytype() {
+            KeyType::RSA if key.size() < MIN_RSA_BITS => report(AuthKeysIssueKind::Weak),
+            KeyType::DSA => report(AuthKeysIssueKind::DeprecatedAlgorithm),
+            _ => {}
+        }
+        match entry.options.map(split_options) {
+            Some(Some(options)) => {
+                for option in options {
+                    if is_suspicious_option(option) {
+                        report(AuthKeysIssueKind::SuspiciousOption(option.to_owned()));
+                    }
+                }
+            }
+            Some(None) => report(AuthKeysIssueKind::Unparseable),
+            None => {}
+        }
+        if !entry.canonical {
+            report(AuthKeysIssueKind::Whitespace);
+        }
+    }
+    issues
+}
+
+/// Parse every key entry of an `authorized_keys` file
+///
+/// The empty lines and the lines starting with `#` are skipped.
+/// The entries are returned with their line numbers, starting from 1,
+/// and a malformed line is reported as an error of its own without stopping the parse.
+/// Only the errors reading from the reader are returned as the outer error.
+///
+/// The keys can then be fingerprinted, e.g. like `ssh-keygen -l -f authorized_keys`:
+/// ```
+/// use osshkeys::keys::{parse_authorized_keys, FingerprintHash, PublicParts};
+///
+/// let input = "no-pty ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIMoWBluPErgKhNja3lHEf7ie6AVzR24mPRd742xEYodC alice\n";
+/// for (line, entry) in parse_authorized_keys(input.as_bytes()).unwrap() {
+///     let entry = entry.unwrap();
+///     println!("{}: {}", line, entry.key.fingerprint_string(FingerprintHash::SHA256).unwrap());
+/// }
+/// ```
+pub fn parse_authorized_keys<R: BufRead>(
+    mut reader: R,
+) -> OsshResult<Vec<(usize, OsshResult<AuthorizedKey>)>> {
+    let mut entries = Vec::new();
+    let mut buf = Vec::new();
+    let mut line = 0;
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        line += 1;
+        let raw = match std::str::from_utf8(&buf) {
+            Ok(raw) => raw.trim_end_matches(['\n', '\r']),
+            Err(_) => {
+                entries.push((line, Err(ErrorKind::InvalidKeyFormat.into())));
+                continue;
+            }
+        };
+        if !is_skipped(raw) {
+            entries.push((line, parse_authorized_key(raw)));
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_authorized_key(raw: &str) -> OsshResult<AuthorizedKey> {
+    let entry = match split_entry(raw) {
+        Some(entry) => entry,
+        None => return Err(ErrorKind::InvalidKeyFormat.into()),
+    };
+    let mut key = match PublicKey::from_keystr(&format!("{} {}", entry.keytype, entry.keyb64)) {
+        Ok(key) => key,
+        // Report why the key is invalid if the line starts with a key type, rather than taking it as the options
+        Err(err) => match entry.options {
+            Some(first) if first.parse::<KeyType>().is_ok() => {
+                let line = raw.trim_matches(|c: char| c.is_ascii_whitespace());
+                return Err(PublicKey::from_keystr(line).err().unwrap_or(err));
+            }
+            _ => return Err(err),
+        },
+    };
+    if entry
+        .options
+        .map_or(false, |opts| split_options(opts).is_none())
+    {
+        return Err(ErrorKind::InvalidKeyFormat.into());
+    }
+    if let Some(comment) = entry.comment {
+        *key.comment_mut() = comment.to_owned();
+    }
+    Ok(AuthorizedKey {
+        options: entry.options.map(str::to_owned),
+        key,
+        comment: entry.comment.map(str::to_owned),
+    })
+}
+
+/// Rewrite an `authorized_keys` file with the canonical whitespace
+///
+/// The leading and trailing whitespace is removed,
+/// and the options, the key type, the key and the comment are separated by one space.
+/// The whitespace inside the comment is kept.
+/// The empty lines, the comment lines and the lines which can't be parsed are kept unchanged.
+pub fn canonicalize_authorized_keys(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for raw in input.lines() {
+        match split_entry(raw).filter(|_| !is_skipped(raw)) {
+            Some(entry) => output.push_str(&entry.to_canonical()),
+            None => output.push_str(raw),
+        }
+        output.push('\n');
+    }
+    output
+}
+
+fn is_skipped(line: &str) -> bool {
+    let line = line.trim_start();
+    line.is_empty() || line.starts_with('#')
+}
+
+// Split the first field, respecting the double quotes and the `\"` escapes inside them
+// Return the field, the rest without the separating whitespace, and whether the separator is one space
+pub(super) fn split_field(s: &str) -> Option<(&str, &str, bool)> {
+    let mut quoted = false;
+    let mut end = s.len();
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' && quoted {
+            chars.next_if(|&(_, next)| next == '"');
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if !quoted && c.is_ascii_whitespace() {
+            end = i;
+            break;
+        }
+    }
+    if quoted {
+        return None;
+    }
+    let rest = &s[end..];
+    let trimmed = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    let single_space = rest.len() - trimmed.len() <= 1 && !rest.starts_with('\t');
+    Some((&s[..end], trimmed, single_space))
+}
+
+fn split_entry(raw: &str) -> Option<Entry<'_>> {
+    let line = raw.trim_matches(|c: char| c.is_ascii_whitespace());
+    let (first, rest, sep1) = split_field(line)?;
+    let (second, rest, sep2) = split_field(rest)?;
+    let mut entry = Entry {
+        options: None,
+        keytype: first,
+        keyb64: second,
+        comment: Some(rest).filter(|c| !c.is_empty()),
+        canonical: line.len() == raw.len() && sep1 && (sep2 || rest.is_empty()),
+    };
+    // The options are absent if the line starts with a key type followed by a valid key
+    if entry.parse_key().is_none() {
+        let (third, rest, sep3) = split_field(rest)?;
+        entry = Entry {
+            options: Some(first),
+            keytype: second,
+            keyb64: third,
+            comment: Some(rest).filter(|c| !c.is_empty()),
+            canonical: line.len() == raw.len() && sep1 && sep2 && (sep3 || rest.is_empty()),
+        };
+    }
+    if entry.keyb64.is_empty() {
+        return None;
+    }
+    Some(entry)
+}
+
+// Split the comma separated options, respecting the double quotes
+//
+// Like sshd, only `\"` is an escape inside the quotes and the other backslashes are literal.
+pub(super) fn split_options(options: &str) -> Option<Vec<&str>> {
+    let mut result = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    let mut chars = options.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' && quoted {
+            chars.next_if(|&(_, next)| next == '"');
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if c == ',' && !quoted {
+            result.push(&options[start..i]);
+            start = i + 1;
+        }
+    }
+    if quoted {
+        return None;
+    }
+    result.push(&options[start..]);
+    if result.iter().any(|opt| opt.is_empty()) {
+        return None;
+    }
+    Some(result)
+}
+
+fn is_suspicious_option(option: &str) -> bool {
+    let (name, value) = match option.split_once('=') {
+        Some((name, value)) => (name, Some(value.trim_matches('"'))),
+        None => (option, None),
+    };
+    let name = name.to_ascii_lowercase();
+    if !KNOWN_OPTIONS.contains(&name.as_str()) {
+        return true;
+    }
+    match (name.as_str(), value) {
+        ("from", Some(value)) => value.split(',').any(|pat| pat == "*"),
+        ("permitopen", Some(value)) | ("permitlisten", Some(value)) => {
+            value == "any" || value == "*:*"
+        }
+        _ => false,
+    }
+}
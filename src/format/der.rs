@@ -1,9 +1,5 @@
 use crate::error::*;
-use crate::keys::{rsa::*, *};
-use openssl::{
-    pkey::{PKey, Public},
-    rsa::Rsa,
-};
+use crate::keys::*;
 
 pub fn to_der_pubkey(pubkey: &PublicKey) -> OsshResult<Vec<u8>> {
     let der = match &pubkey.key {
@@ -11,8 +7,99 @@ pub fn to_der_pubkey(pubkey: &PublicKey) -> OsshResult<Vec<u8>> {
         PublicKeyType::DSA(key) => key.ossl_pkey()?.public_key_to_der()?,
         PublicKeyType::ECDSA(key) => key.ossl_pkey()?.public_key_to_der()?,
         PublicKeyType::ED25519(key) => key.ossl_pkey()?.public_key_to_der()?,
+        PublicKeyType::ECDSA_SK(_) | PublicKeyType::ED25519_SK(_) | PublicKeyType::XMSS(_) => {
+            return Err(ErrorKind::UnsupportType.into())
+        }
     };
 
     Ok(der)
 }
 
+/// Check if the DSA/ECDSA signature is a strictly canonical DER encoded `SEQUENCE { r INTEGER, s INTEGER }`
+///
+/// Non-minimal lengths, leading zeros, negative integers and trailing data are rejected.
+pub(crate) fn is_canonical_dsa_sig(sig: &[u8]) -> bool {
+    fn read_tlv<'a>(buf: &'a [u8], pos: &mut usize, tag: u8) -> Option<&'a [u8]> {
+        if *buf.get(*pos)? != tag {
+            return None;
+        }
+        let first = *buf.get(*pos + 1)?;
+        *pos += 2;
+        let len = match first {
+            0x00..=0x7f => first as usize,
+            0x81 => {
+                let len = *buf.get(*pos)? as usize;
+                *pos += 1;
+                if len < 0x80 {
+                    return None;
+                }
+                len
+            }
+            0x82 => {
+                let len = u16::from_be_bytes([*buf.get(*pos)?, *buf.get(*pos + 1)?]) as usize;
+                *pos += 2;
+                if len < 0x100 {
+                    return None;
+                }
+                len
+            }
+            _ => return None,
+        };
+        let content = buf.get(*pos..*pos + len)?;
+        *pos += len;
+        Some(content)
+    }
+    fn is_canonical_uint(int: &[u8]) -> bool {
+        match int {
+            [] => false,
+            [first, ..] if first & 0x80 != 0 => false,
+            [0, second, ..] if second & 0x80 == 0 => false,
+            _ => true,
+        }
+    }
+
+    let mut pos = 0;
+    let seq = match read_tlv(sig, &mut pos, 0x30) {
+        Some(seq) if pos == sig.len() => seq,
+        _ => return false,
+    };
+    let mut pos = 0;
+    let r = read_tlv(seq, &mut pos, 0x02);
+    let s = read_tlv(seq, &mut pos, 0x02);
+    match (r, s) {
+        (Some(r), Some(s)) => pos == seq.len() && is_canonical_uint(r) && is_canonical_uint(s),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonical_dsa_sig() {
+        assert!(is_canonical_dsa_sig(&[
+            0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x7f
+        ]));
+        assert!(is_canonical_dsa_sig(&[
+            0x30, 0x07, 0x02, 0x02, 0x00, 0x80, 0x02, 0x01, 0x01
+        ]));
+        // Leading zero
+        assert!(!is_canonical_dsa_sig(&[
+            0x30, 0x07, 0x02, 0x02, 0x00, 0x01, 0x02, 0x01, 0x01
+        ]));
+        // Negative
+        assert!(!is_canonical_dsa_sig(&[
+            0x30, 0x06, 0x02, 0x01, 0x80, 0x02, 0x01, 0x01
+        ]));
+        // Non-minimal length
+        assert!(!is_canonical_dsa_sig(&[
+            0x30, 0x81, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01
+        ]));
+        // Trailing data
+        assert!(!is_canonical_dsa_sig(&[
+            0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, 0x00
+        ]));
+        assert!(!is_canonical_dsa_sig(&[]));
+    }
+}
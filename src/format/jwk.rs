@@ -0,0 +1,383 @@
+use crate::error::*;
+use crate::keys::{ecdsa::*, *};
+use base64::prelude::*;
+use openssl::bn::{BigNum, BigNumContext, BigNumRef};
+use std::collections::HashMap;
+
+/// Serialize the public key as a JSON Web Key (RFC 7517)
+///
+/// - RSA: `{"kty":"RSA","n":...,"e":...}`
+/// - ECDSA: `{"kty":"EC","crv":"P-256","x":...,"y":...}`, also `P-384`, `P-521` and `secp256k1` (RFC 8812)
+/// - Ed25519: `{"kty":"OKP","crv":"Ed25519","x":...}` (RFC 8037)
+///
+/// The values are encoded as base64url without padding,
+/// and the `kid` is the SHA256 fingerprint as printed by `ssh-keygen -l`.
+/// The other keys, e.g. DSA, have no JWK representation and return
+/// [`ErrorKind::UnsupportType`](../../error/enum.ErrorKind.html#variant.UnsupportType).
+pub fn stringify_jwk(pubkey: &PublicKey) -> OsshResult<String> {
+    let fields = match &pubkey.key {
+        PublicKeyType::RSA(key) => {
+            let rsa = key.ossl_rsa();
+            format!(
+                r#""kty":"RSA","n":"{}","e":"{}""#,
+                b64url(&rsa.n().to_vec()),
+                b64url(&rsa.e().to_vec())
+            )
+        }
+        PublicKeyType::ECDSA(key) => {
+            let crv = match key.curve() {
+                EcCurve::Nistp256 => "P-256",
+                EcCurve::Nistp384 => "P-384",
+                EcCurve::Nistp521 => "P-521",
+                EcCurve::Secp256k1 => "secp256k1",
+            };
+            let ec = key.ossl_ec();
+            let mut ctx = BigNumContext::new()?;
+            let mut x = BigNum::new()?;
+            let mut y = BigNum::new()?;
+            ec.public_key()
+                .affine_coordinates(ec.group(), &mut x, &mut y, &mut ctx)?;
+            let len = (key.curve().size() + 7) / 8;
+            format!(
+                r#""kty":"EC","crv":"{}","x":"{}","y":"{}""#,
+                crv,
+                padded_b64url(&x, len)?,
+                padded_b64url(&y, len)?
+            )
+        }
+        PublicKeyType::ED25519(key) => {
+            format!(
+                r#""kty":"OKP","crv":"Ed25519","x":"{}""#,
+                b64url(key.as_bytes())
+            )
+        }
+        PublicKeyType::DSA(_)
+        | PublicKeyType::ECDSA_SK(_)
+        | PublicKeyType::ED25519_SK(_)
+        | PublicKeyType::XMSS(_) => return Err(ErrorKind::UnsupportType.into()),
+    };
+    let kid = pubkey.fingerprint_string(FingerprintHash::SHA256)?;
+    Ok(format!(r#"{{{},"kid":"{}"}}"#, fields, kid))
+}
+
+/// Parse the JSON Web Key (RFC 7517) of a public key
+///
+/// This is the reverse of [`stringify_jwk()`](fn.stringify_jwk.html).
+/// The `kty` and `crv` decide the key type, and the other members except the key parameters are ignored.
+///
+/// The invalid base64url parameters return [`ErrorKind::Base64Error`](../../error/enum.ErrorKind.html#variant.Base64Error),
+/// the unknown `kty` returns [`ErrorKind::UnsupportType`](../../error/enum.ErrorKind.html#variant.UnsupportType),
+/// and the unknown `crv` returns [`ErrorKind::UnsupportCurve`](../../error/enum.ErrorKind.html#variant.UnsupportCurve).
+/// The malformed JSON and the missing parameters return
+/// [`ErrorKind::InvalidKeyFormat`](../../error/enum.ErrorKind.html#variant.InvalidKeyFormat).
+pub fn parse_jwk(json: &str) -> OsshResult<PublicKey> {
+    let members = parse_json_object(json).ok_or(ErrorKind::InvalidKeyFormat)?;
+    let member = |name: &str| {
+        members
+            .get(name)
+            .ok_or_else(|| Error::with_failure(ErrorKind::InvalidKeyFormat, name))
+    };
+    let param =
+        |name: &str| -> OsshResult<Vec<u8>> { Ok(BASE64_URL_SAFE_NO_PAD.decode(member(name)?)?) };
+
+    match member("kty")?.as_str() {
+        "RSA" => PublicKeyBuilder::rsa(&param("n")?, &param("e")?).build(),
+        "EC" => {
+            let curve = match member("crv")?.as_str() {
+                "P-256" => EcCurve::Nistp256,
+                "P-384" => EcCurve::Nistp384,
+                "P-521" => EcCurve::Nistp521,
+                "secp256k1" => EcCurve::Secp256k1,
+                crv => return Err(Error::with_failure(ErrorKind::UnsupportCurve, crv)),
+            };
+            let (x, y) = (param("x")?, param("y")?);
+            let len = (curve.size() + 7) / 8;
+            if x.len() != len || y.len() != len {
+                return Err(ErrorKind::InvalidLength.into());
+            }
+            let mut point = Vec::with_capacity(1 + 2 * len);
+            point.push(0x04);
+            point.extend_from_slice(&x);
+            point.extend_from_slice(&y);
+            PublicKeyBuilder::ecdsa(curve, &point).build()
+        }
+        "OKP" => match member("crv")?.as_str() {
+            "Ed25519" => PublicKeyBuilder::ed25519(&param("x")?).build(),
+            crv => Err(Error::with_failure(ErrorKind::UnsupportCurve, crv)),
+        },
+        kty => Err(Error::with_failure(ErrorKind::UnsupportType, kty)),
+    }
+}
+
+// Parse the members of a JSON object with the string values, the other values are skipped
+fn parse_json_object(json: &str) -> Option<HashMap<String, String>> {
+    let mut chars = json.trim().chars().peekable();
+    let mut members = HashMap::new();
+    if chars.next()? != '{' {
+        return None;
+    }
+    skip_json_whitespace(&mut chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+    } else {
+        loop {
+            skip_json_whitespace(&mut chars);
+            if chars.next()? != '"' {
+                return None;
+            }
+            let name = parse_json_string(&mut chars)?;
+            skip_json_whitespace(&mut chars);
+            if chars.next()? != ':' {
+                return None;
+            }
+            skip_json_whitespace(&mut chars);
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                members.insert(name, parse_json_string(&mut chars)?);
+            } else {
+                skip_json_value(&mut chars)?;
+            }
+            skip_json_whitespace(&mut chars);
+            match chars.next()? {
+                ',' => continue,
+                '}' => break,
+                _ => return None,
+            }
+        }
+    }
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(members)
+}
+
+type JsonChars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn skip_json_whitespace(chars: &mut JsonChars) {
+    while chars
+        .next_if(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        .is_some()
+    {}
+}
+
+// Parse the rest of the string after the opening quote
+fn parse_json_string(chars: &mut JsonChars) -> Option<String> {
+    let mut s = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(s),
+            '\\' => s.push(match chars.next()? {
+                '"' => '"',
+                '\\' => '\\',
+                '/' => '/',
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => match parse_json_hex4(chars)? {
+                    // The characters outside the BMP are escaped as the UTF-16 surrogate pair
+                    high @ 0xd800..=0xdbff => {
+                        if chars.next()? != '\\' || chars.next()? != 'u' {
+                            return None;
+                        }
+                        let low = parse_json_hex4(chars)?;
+                        if !(0xdc00..=0xdfff).contains(&low) {
+                            return None;
+                        }
+                        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))?
+                    }
+                    // The unpaired low surrogate is rejected by char::from_u32()
+                    unit => char::from_u32(unit)?,
+                },
+                _ => return None,
+            }),
+            c if c < ' ' => return None,
+            c => s.push(c),
+        }
+    }
+}
+
+fn parse_json_hex4(chars: &mut JsonChars) -> Option<u32> {
+    let hex: String = chars.by_ref().take(4).collect();
+    if hex.len() != 4 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+// Skip the number, literal, array or object value, which are never used by the keys
+fn skip_json_value(chars: &mut JsonChars) -> Option<()> {
+    let mut depth = 0usize;
+    loop {
+        match chars.peek()? {
+            '"' => {
+                chars.next();
+                parse_json_string(chars)?;
+            }
+            '[' | '{' => {
+                chars.next();
+                depth += 1;
+            }
+            ']' | '}' if depth > 0 => {
+                chars.next();
+                depth -= 1;
+            }
+            ',' | '}' if depth == 0 => return Some(()),
+            _ => {
+                chars.next();
+            }
+        }
+    }
+}
+
+fn b64url(data: &[u8]) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(data)
+}
+
+// The EC coordinates are always the full length of the field
+fn padded_b64url(num: &BigNumRef, len: usize) -> OsshResult<String> {
+    Ok(b64url(&num.to_vec_padded(len as i32)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys::ed25519::Ed25519PublicKey;
+    use hex_literal::hex;
+
+    #[test]
+    fn jwk_ed25519_rfc8037() {
+        // RFC 8037 appendix A.2
+        let key = Ed25519PublicKey::new(&hex!(
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+        ))
+        .unwrap();
+        let pubkey = PublicKey::from(key);
+        let kid = pubkey.fingerprint_string(FingerprintHash::SHA256).unwrap();
+        assert_eq!(
+            stringify_jwk(&pubkey).unwrap(),
+            format!(
+                r#"{{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo","kid":"{}"}}"#,
+                kid
+            )
+        );
+    }
+
+    #[test]
+    fn jwk_parse() {
+        for (keytype, bits) in [
+            (KeyType::RSA, 2048),
+            (KeyType::ECDSA, 256),
+            (KeyType::ECDSA, 384),
+            (KeyType::ECDSA, 521),
+            (KeyType::ED25519, 256),
+        ] {
+            let pubkey = KeyPair::generate(keytype, bits)
+                .unwrap()
+                .clone_public_key()
+                .unwrap();
+            let jwk = stringify_jwk(&pubkey).unwrap();
+            let parsed = parse_jwk(&jwk).unwrap();
+            assert_eq!(parsed.blob().unwrap(), pubkey.blob().unwrap());
+        }
+
+        // RFC 8037 appendix A.2, with the other members and the whitespace
+        let jwk = r#" {
+            "kty" : "OKP", "crv": "Ed25519",
+            "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo",
+            "key_ops": ["verify"], "ext": true, "use": "sig\u0020\"x\"", "n": {"a": [1, "}"]}
+        } "#;
+        let parsed = parse_jwk(jwk).unwrap();
+        assert_eq!(
+            parsed.blob().unwrap()[19..],
+            hex!("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+        );
+
+        for (jwk, kind) in [
+            (
+                r#"{"kty":"OKP","crv":"Ed25519","x":"11qY+YKx"}"#,
+                ErrorKind::Base64Error,
+            ),
+            (r#"{"kty":"oct","k":"AAAA"}"#, ErrorKind::UnsupportType),
+            (
+                r#"{"kty":"OKP","crv":"X25519","x":"AAAA"}"#,
+                ErrorKind::UnsupportCurve,
+            ),
+            (
+                r#"{"kty":"EC","crv":"P-256","x":"AAAA"}"#,
+                ErrorKind::InvalidKeyFormat,
+            ),
+            (
+                r#"{"kty":"RSA","n":"AQAB","e":"AQAB""#,
+                ErrorKind::InvalidKeyFormat,
+            ),
+            (r#"{"kty":"RSA"} {}"#, ErrorKind::InvalidKeyFormat),
+            (r#"["kty","RSA"]"#, ErrorKind::InvalidKeyFormat),
+        ] {
+            assert_eq!(parse_jwk(jwk).err().unwrap().kind(), kind, "{}", jwk);
+        }
+    }
+
+    #[test]
+    fn jwk_string_escapes() {
+        let parse = |s: &str| parse_json_string(&mut s.chars().peekable());
+        assert_eq!(parse(r#"a\u00e9\u20ac""#).unwrap(), "a\u{e9}\u{20ac}");
+        assert_eq!(parse(r#"\ud83d\ude00x""#).unwrap(), "\u{1f600}x");
+        assert_eq!(parse(r#"\uD834\uDD1E""#).unwrap(), "\u{1d11e}");
+        for invalid in [
+            r#"\ud83d""#,
+            r#"\ud83dx""#,
+            r#"\ud83d\u0041""#,
+            r#"\ude00""#,
+            r#"\u+123""#,
+            r#"\u12""#,
+        ] {
+            assert!(parse(invalid).is_none(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn jwk_rsa_ecdsa() {
+        let rsa = KeyPair::generate(KeyType::RSA, 2048).unwrap();
+        let jwk = stringify_jwk(&rsa.clone_public_key().unwrap()).unwrap();
+        assert!(jwk.starts_with(r#"{"kty":"RSA","n":""#));
+        assert!(jwk.contains(r#","e":"AQAB","kid":"SHA256:"#));
+
+        for (bits, crv, len) in [(256, "P-256", 32), (384, "P-384", 48), (521, "P-521", 66)] {
+            let ecdsa = KeyPair::generate(KeyType::ECDSA, bits).unwrap();
+            let jwk = stringify_jwk(&ecdsa.clone_public_key().unwrap()).unwrap();
+            assert!(jwk.starts_with(&format!(r#"{{"kty":"EC","crv":"{}","x":""#, crv)));
+            let x = jwk.split('"').nth(11).unwrap();
+            let y = jwk.split('"').nth(15).unwrap();
+            let mut point = vec![0x04];
+            point.extend(BASE64_URL_SAFE_NO_PAD.decode(x).unwrap());
+            point.extend(BASE64_URL_SAFE_NO_PAD.decode(y).unwrap());
+            assert_eq!(point.len(), 1 + 2 * len);
+
+            let ec = match &ecdsa.key {
+                KeyPairType::ECDSA(key) => key.ossl_ec(),
+                _ => unreachable!(),
+            };
+            let mut ctx = BigNumContext::new().unwrap();
+            let expected = ec
+                .public_key()
+                .to_bytes(
+                    ec.group(),
+                    openssl::ec::PointConversionForm::UNCOMPRESSED,
+                    &mut ctx,
+                )
+                .unwrap();
+            assert_eq!(point, expected);
+        }
+
+        let dsa = KeyPair::generate(KeyType::DSA, 1024).unwrap();
+        assert_eq!(
+            stringify_jwk(&dsa.clone_public_key().unwrap())
+                .err()
+                .unwrap()
+                .kind(),
+            ErrorKind::UnsupportType
+        );
+    }
+}
@@ -1,27 +1,69 @@
 use crate::error::*;
 use crate::keys::*;
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
 
+pub mod allowed_signers;
+pub mod authorized_keys;
+pub mod der;
+pub mod diff;
+pub mod jwk;
 pub mod ossh_privkey;
 pub mod ossh_pubkey;
 pub mod pem;
-pub mod der;
 pub mod pkcs8;
+pub mod putty;
+pub mod rfc4716;
+pub mod sshsig;
+
+pub use allowed_signers::find_principals;
+pub use authorized_keys::{
+    canonicalize_authorized_keys, lint_authorized_keys, parse_authorized_keys, AuthKeysIssue,
+    AuthKeysIssueKind, AuthorizedKey,
+};
+pub use diff::{diff_keys, KeyDiff, KeyFileInfo, KeyFormat};
 
 pub fn parse_keystr(pem: &[u8], passphrase: Option<&str>) -> OsshResult<KeyPair> {
+    parse_keystr_with_options(pem, passphrase, &ParseOptions::default())
+}
+
+// Parse the private key, applying the limits in the OpenSSH and PuTTY decoders
+// before the key derivation and the other expensive work
+//
+// The PEM and PKCS#8 keys are parsed by OpenSSL, so the caller checks them afterwards.
+pub(crate) fn parse_keystr_with_options(
+    pem: &[u8],
+    passphrase: Option<&str>,
+    options: &ParseOptions,
+) -> OsshResult<KeyPair> {
+    let pem = &*normalize_line_endings(pem);
+    let pem = &*normalize_pem_end(pem);
+    if putty::is_putty_privkey(pem) {
+        // PuTTY format
+        return putty::decode_putty_priv_with_options(pem, passphrase.map(str::as_bytes), options);
+    }
     let pemdata = ::pem::parse(pem)?;
 
     match pemdata.tag() {
         "OPENSSH PRIVATE KEY" => {
             // Openssh format
-            ossh_privkey::decode_ossh_priv(pemdata.contents(), passphrase)
+            ossh_privkey::decode_ossh_priv_with_options(pemdata.contents(), passphrase, options)
         }
         "PRIVATE KEY" => {
             // PKCS#8 format
             pem::parse_pem_privkey(pem, passphrase)
         }
         "ENCRYPTED PRIVATE KEY" => {
-            // PKCS#8 format
-            pem::parse_pem_privkey(pem, passphrase)
+            // Encrypted PKCS#8 format
+            let passphrase = passphrase.ok_or(ErrorKind::PassphraseRequired)?;
+            pem::parse_pem_privkey(pem, Some(passphrase))
+        }
+        "DSA PRIVATE KEY" | "RSA PRIVATE KEY" | "EC PRIVATE KEY"
+            if passphrase.is_none() && pemdata.headers().get("Proc-Type").is_some() =>
+        {
+            // Encrypted Openssl Key
+            Err(ErrorKind::PassphraseRequired.into())
         }
         "DSA PRIVATE KEY" => {
             // Openssl DSA Key
@@ -39,6 +81,204 @@ pub fn parse_keystr(pem: &[u8], passphrase: Option<&str>) -> OsshResult<KeyPair>
             // Openssl Ed25519 Key
             pem::parse_pem_privkey(pem, passphrase)
         }
-        _ => Err(ErrorKind::UnsupportType.into()),
+        tag => Err(Error::with_failure(ErrorKind::UnsupportType, tag)),
+    }
+}
+
+// Convert the lone `\r` line endings (used by the classic Mac OS) to `\n`, the `\r\n` ones are kept
+pub(crate) fn normalize_line_endings(data: &[u8]) -> Cow<'_, [u8]> {
+    let is_lone_cr = |i: usize| data[i] == b'\r' && data.get(i + 1) != Some(&b'\n');
+    if !(0..data.len()).any(is_lone_cr) {
+        return Cow::Borrowed(data);
+    }
+    let normalized = (0..data.len())
+        .map(|i| if is_lone_cr(i) { b'\n' } else { data[i] })
+        .collect();
+    Cow::Owned(normalized)
+}
+
+// Put the PEM end marker on its own line, followed by a newline, which is required by OpenSSL
+//
+// The newlines around the end marker may be stripped when the key is pasted into a web form.
+pub(crate) fn normalize_pem_end(data: &[u8]) -> Cow<'_, [u8]> {
+    const END_MARKER: &[u8] = b"-----END ";
+    let pos = match data.windows(END_MARKER.len()).position(|w| w == END_MARKER) {
+        Some(pos) => pos,
+        None => return Cow::Borrowed(data),
+    };
+    let joined = pos > 0 && data[pos - 1] != b'\n';
+    let unterminated = !data.ends_with(b"\n");
+    if !joined && !unterminated {
+        return Cow::Borrowed(data);
+    }
+    let mut normalized = Vec::with_capacity(data.len() + 2);
+    normalized.extend_from_slice(&data[..pos]);
+    if joined {
+        normalized.push(b'\n');
+    }
+    normalized.extend_from_slice(&data[pos..]);
+    if unterminated {
+        normalized.push(b'\n');
+    }
+    Cow::Owned(normalized)
+}
+
+pub(crate) fn normalize_line_endings_str(s: &str) -> Cow<'_, str> {
+    match normalize_line_endings(s.as_bytes()) {
+        Cow::Borrowed(_) => Cow::Borrowed(s),
+        // Only the ASCII `\r` is replaced, so it is still valid UTF-8
+        Cow::Owned(data) => Cow::Owned(String::from_utf8(data).unwrap()),
+    }
+}
+
+// Reject the comment with a line break, which would start another line in the key file
+pub(crate) fn check_comment(comment: &[u8]) -> OsshResult<()> {
+    if comment.iter().any(|&b| b == b'\n' || b == b'\r') {
+        return Err(ErrorKind::InvalidArgument.into());
+    }
+    Ok(())
+}
+
+// Remove the ASCII control characters from the comment
+//
+// The control characters are never a part of a multibyte UTF-8 sequence, so the valid UTF-8 stays valid.
+pub(crate) fn sanitize_comment(comment: &[u8]) -> Vec<u8> {
+    comment
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_control())
+        .collect()
+}
+
+/// Check the integrity of a private key file without the passphrase
+///
+/// For the OpenSSH format, the magic, the header fields, the public key and the lengths of all sections are checked,
+/// which doesn't require decrypting the private section.
+/// For the other formats, only the PEM container is checked.
+///
+/// Truncated data is reported as [`ErrorKind::InvalidLength`](../error/enum.ErrorKind.html#variant.InvalidLength),
+/// and corrupted data is usually reported as [`ErrorKind::InvalidKeyFormat`](../error/enum.ErrorKind.html#variant.InvalidKeyFormat).
+pub fn validate_structure(data: &str) -> OsshResult<()> {
+    let pemdata = ::pem::parse(data)?;
+
+    match pemdata.tag() {
+        "OPENSSH PRIVATE KEY" => ossh_privkey::validate_ossh_priv(pemdata.contents()),
+        "PRIVATE KEY"
+        | "ENCRYPTED PRIVATE KEY"
+        | "DSA PRIVATE KEY"
+        | "RSA PRIVATE KEY"
+        | "EC PRIVATE KEY" => Ok(()),
+        tag => Err(Error::with_failure(ErrorKind::UnsupportType, tag)),
+    }
+}
+
+/// Fingerprint every key file inside a directory
+///
+/// The directory is walked recursively (without following symbolic links).
+/// Public key files are fingerprinted directly,
+/// and the public parts of unencrypted private keys are used for private key files.
+/// Files which don't look like a key are skipped,
+/// while key files which cannot be read (e.g. encrypted private keys) are reported with their error.
+///
+/// The fingerprints are formatted by [`PublicParts::fingerprint_string()`](../keys/trait.PublicParts.html#method.fingerprint_string),
+/// and the result is sorted by the path.
+pub fn fingerprint_dir(path: &Path, hash: FingerprintHash) -> Vec<(PathBuf, OsshResult<String>)> {
+    let mut result = Vec::new();
+    walk_fingerprint_dir(path, hash, &mut result);
+    result.sort_by(|a, b| a.0.cmp(&b.0));
+    result
+}
+
+fn walk_fingerprint_dir(
+    path: &Path,
+    hash: FingerprintHash,
+    result: &mut Vec<(PathBuf, OsshResult<String>)>,
+) {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            result.push((path.to_owned(), Err(e.into())));
+            return;
+        }
+    };
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                result.push((path.to_owned(), Err(e.into())));
+                continue;
+            }
+        };
+        let entry_path = entry.path();
+        match entry.file_type() {
+            Ok(t) if t.is_dir() => walk_fingerprint_dir(&entry_path, hash, result),
+            Ok(t) if t.is_file() => {
+                if let Some(fp) = fingerprint_file(&entry_path, hash) {
+                    result.push((entry_path, fp));
+                }
+            }
+            Ok(_) => {}
+            Err(e) => result.push((entry_path, Err(e.into()))),
+        }
+    }
+}
+
+fn fingerprint_file(path: &Path, hash: FingerprintHash) -> Option<OsshResult<String>> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) => return Some(Err(e.into())),
+    };
+    // Key files are always text
+    let keystr = std::str::from_utf8(&data).ok()?;
+
+    if let Ok(pubkey) = PublicKey::from_keystr(keystr) {
+        return Some(pubkey.fingerprint_string(hash));
+    }
+    match KeyPair::from_keystr(keystr, None) {
+        Ok(keypair) => Some(keypair.fingerprint_string(hash)),
+        Err(e) if keystr.trim_start().starts_with("-----BEGIN") => Some(Err(e)),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn line_endings_normalized() {
+        assert_eq!(
+            &*normalize_line_endings(b"a\rb\r\nc\nd\r"),
+            b"a\nb\r\nc\nd\n"
+        );
+        assert!(matches!(
+            normalize_line_endings(b"a\r\nb\n"),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            normalize_line_endings_str("\u{e9}\r\u{e9}"),
+            "\u{e9}\n\u{e9}"
+        );
+    }
+
+    #[test]
+    fn pem_end_normalized() {
+        assert_eq!(
+            &*normalize_pem_end(b"-----BEGIN X-----\nAAAA-----END X-----"),
+            b"-----BEGIN X-----\nAAAA\n-----END X-----\n"
+        );
+        assert_eq!(
+            &*normalize_pem_end(b"-----BEGIN X-----\r\nAAAA\r\n-----END X-----"),
+            b"-----BEGIN X-----\r\nAAAA\r\n-----END X-----\n"
+        );
+        assert!(matches!(
+            normalize_pem_end(b"-----BEGIN X-----\nAAAA\n-----END X-----\n"),
+            Cow::Borrowed(_)
+        ));
+        // Not a PEM file
+        assert!(matches!(
+            normalize_pem_end(b"PuTTY-User-Key-File-3: ssh-ed25519"),
+            Cow::Borrowed(_)
+        ));
     }
 }
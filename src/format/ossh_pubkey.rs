@@ -1,5 +1,9 @@
 use crate::error::*;
-use crate::keys::{dsa::*, ecdsa::*, ed25519::*, rsa::*, PublicKey, PublicParts};
+use crate::format::check_comment;
+use crate::keys::{
+    dsa::*, ecdsa::*, ed25519::*, rsa::*, xmss::*, ParseOptions, ParseWarning, PublicKey,
+    PublicParts,
+};
 use crate::sshbuf::{SshReadExt, SshWriteExt};
 use base64::prelude::*;
 use ed25519_dalek::VerifyingKey as Ed25519PubKey;
@@ -14,31 +18,143 @@ use std::io;
 use std::str::FromStr;
 
 pub fn parse_ossh_pubkey(keystr: &str) -> OsshResult<PublicKey> {
-    let key_split: Vec<&str> = keystr.split_ascii_whitespace().collect();
-    if key_split.len() < 2 || key_split.len() > 3 {
-        return Err(ErrorKind::InvalidKeyFormat.into());
+    parse_ossh_pubkey_with_options(keystr, &ParseOptions::default())
+}
+
+// Parse the openssh public key, applying the limits while decoding the blob
+pub(crate) fn parse_ossh_pubkey_with_options(
+    keystr: &str,
+    options: &ParseOptions,
+) -> OsshResult<PublicKey> {
+    Ok(parse_ossh_pubkey_impl(keystr.as_bytes(), false, options)?.0)
+}
+
+/// Parse the openssh public key, also accepting the URL-safe base64 alphabet
+pub fn parse_ossh_pubkey_lenient(keystr: &str) -> OsshResult<PublicKey> {
+    Ok(parse_ossh_pubkey_impl(keystr.as_bytes(), true, &ParseOptions::default())?.0)
+}
+
+/// Parse the openssh public key like [`parse_ossh_pubkey_lenient()`](fn.parse_ossh_pubkey_lenient.html),
+/// also returning what has been fixed in the base64 string
+pub fn parse_ossh_pubkey_lenient_with_warning(
+    keystr: &str,
+) -> OsshResult<(PublicKey, Option<ParseWarning>)> {
+    parse_ossh_pubkey_impl(keystr.as_bytes(), true, &ParseOptions::default())
+}
+
+/// Parse the openssh public key from the borrowed bytes
+///
+/// The fields are parsed in place, so only the decoded blob and the comment are allocated.
+pub fn parse_ossh_pubkey_bytes(keydata: &[u8]) -> OsshResult<PublicKey> {
+    Ok(parse_ossh_pubkey_impl(keydata, false, &ParseOptions::default())?.0)
+}
+
+fn decode_base64_lenient(data: &[u8]) -> OsshResult<(Vec<u8>, Option<ParseWarning>)> {
+    let err = match BASE64_STANDARD.decode(data) {
+        Ok(blob) => return Ok((blob, None)),
+        Err(err) => err,
+    };
+    // Strip all the padding and retry once without it
+    let padding = data.iter().rev().take_while(|&&c| c == b'=').count();
+    let data = &data[..data.len() - padding];
+    let expected = (4 - data.len() % 4) % 4;
+    if let Ok(blob) = BASE64_STANDARD_NO_PAD.decode(data) {
+        let warning = if padding > expected {
+            ParseWarning::ExcessPadding {
+                removed: padding - expected,
+            }
+        } else {
+            ParseWarning::MissingPadding
+        };
+        return Ok((blob, Some(warning)));
+    }
+    match BASE64_URL_SAFE_NO_PAD.decode(data) {
+        Ok(blob) => Ok((blob, Some(ParseWarning::UrlSafeBase64))),
+        Err(_) => Err(err.into()),
     }
-    let blob = BASE64_STANDARD.decode(key_split[1])?;
-    let mut pubkey: PublicKey = match key_split[0] {
+}
+
+fn parse_ossh_pubkey_impl(
+    keydata: &[u8],
+    lenient: bool,
+    options: &ParseOptions,
+) -> OsshResult<(PublicKey, Option<ParseWarning>)> {
+    let mut fields = keydata
+        .split(|c| c.is_ascii_whitespace())
+        .filter(|field| !field.is_empty());
+    let (keyname, keyb64, comment) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(keyname), Some(keyb64), comment) if fields.next().is_none() => {
+            (keyname, keyb64, comment)
+        }
+        _ => return Err(ErrorKind::InvalidKeyFormat.into()),
+    };
+    let keyname = std::str::from_utf8(keyname).map_err(|_| ErrorKind::InvalidKeyFormat)?;
+    let (blob, warning) = if lenient {
+        decode_base64_lenient(keyb64)?
+    } else {
+        (BASE64_STANDARD.decode(keyb64)?, None)
+    };
+    let mut pubkey: PublicKey = match keyname {
         RSA_NAME | RSA_SHA256_NAME | RSA_SHA512_NAME => {
-            let mut rsa = decode_rsa_pubkey(&blob)?;
-            rsa.set_sign_type(RsaSignature::from_name(key_split[0]).unwrap());
+            let mut rsa = decode_rsa_pubkey(&blob, options.max_rsa_bits)?;
+            rsa.set_sign_type(RsaSignature::from_name(keyname).unwrap());
             rsa.into()
         }
         DSA_NAME => decode_dsa_pubkey(&blob)?.into(),
         NIST_P256_NAME => decode_ecdsa_pubkey(&blob, Some(EcCurve::Nistp256))?.into(),
         NIST_P384_NAME => decode_ecdsa_pubkey(&blob, Some(EcCurve::Nistp384))?.into(),
         NIST_P521_NAME => decode_ecdsa_pubkey(&blob, Some(EcCurve::Nistp521))?.into(),
+        SECP256K1_NAME => decode_ecdsa_pubkey(&blob, Some(EcCurve::Secp256k1))?.into(),
+        ECDSA_SK_NAME => decode_ecdsa_sk_pubkey(&blob)?.into(),
         ED25519_NAME => decode_ed25519_pubkey(&blob)?.into(),
-        _ => return Err(ErrorKind::UnsupportType.into()),
+        ED25519_SK_NAME => decode_ed25519_sk_pubkey(&blob)?.into(),
+        XMSS_NAME => decode_xmss_pubkey(&blob)?.into(),
+        _ => return Err(Error::with_failure(ErrorKind::UnsupportType, keyname)),
     };
-    if key_split.len() == 3 {
-        *pubkey.comment_mut() = key_split[2].to_owned();
+    if let Some(comment) = comment {
+        pubkey.set_comment_bytes(comment);
+    }
+    Ok((pubkey, warning))
+}
+
+/// Peek the key name of the blob without copying it
+pub(crate) fn blob_keyname(keyblob: &[u8]) -> OsshResult<&str> {
+    if keyblob.len() < 4 {
+        return Err(ErrorKind::InvalidLength.into());
     }
+    let len = u32::from_be_bytes([keyblob[0], keyblob[1], keyblob[2], keyblob[3]]) as usize;
+    let name = keyblob.get(4..4 + len).ok_or(ErrorKind::InvalidLength)?;
+    Ok(std::str::from_utf8(name).map_err(|_| ErrorKind::InvalidKeyFormat)?)
+}
+
+pub(crate) fn decode_ossh_pubkey_blob(keyblob: &[u8]) -> OsshResult<PublicKey> {
+    decode_ossh_pubkey_blob_with_options(keyblob, &ParseOptions::default())
+}
+
+pub(crate) fn decode_ossh_pubkey_blob_with_options(
+    keyblob: &[u8],
+    options: &ParseOptions,
+) -> OsshResult<PublicKey> {
+    let keyname = blob_keyname(keyblob)?;
+    let pubkey = match keyname {
+        RSA_NAME | RSA_SHA256_NAME | RSA_SHA512_NAME => {
+            decode_rsa_pubkey(keyblob, options.max_rsa_bits)?.into()
+        }
+        DSA_NAME => decode_dsa_pubkey(keyblob)?.into(),
+        NIST_P256_NAME | NIST_P384_NAME | NIST_P521_NAME | SECP256K1_NAME => {
+            decode_ecdsa_pubkey(keyblob, Some(EcCurve::from_name(keyname)?))?.into()
+        }
+        ECDSA_SK_NAME => decode_ecdsa_sk_pubkey(keyblob)?.into(),
+        ED25519_NAME => decode_ed25519_pubkey(keyblob)?.into(),
+        ED25519_SK_NAME => decode_ed25519_sk_pubkey(keyblob)?.into(),
+        XMSS_NAME => decode_xmss_pubkey(keyblob)?.into(),
+        _ => return Err(Error::with_failure(ErrorKind::UnsupportType, keyname)),
+    };
     Ok(pubkey)
 }
 
-pub(crate) fn decode_rsa_pubkey(keyblob: &[u8]) -> OsshResult<RsaPublicKey> {
+// The modulus larger than `max_bits` is rejected before the key is built
+pub(crate) fn decode_rsa_pubkey(keyblob: &[u8], max_bits: usize) -> OsshResult<RsaPublicKey> {
     let mut reader = io::Cursor::new(keyblob);
     let keyname = reader.read_utf8()?;
     if keyname != RSA_NAME && keyname != RSA_SHA256_NAME && keyname != RSA_SHA512_NAME {
@@ -46,6 +162,7 @@ pub(crate) fn decode_rsa_pubkey(keyblob: &[u8]) -> OsshResult<RsaPublicKey> {
     }
     let e = reader.read_mpint()?;
     let n = reader.read_mpint()?;
+    check_rsa_modulus(&n, max_bits.min(RSA_MAX_SIZE))?;
 
     Ok(RsaPublicKey::new(n, e)?)
 }
@@ -81,7 +198,21 @@ pub(crate) fn decode_ecdsa_pubkey(
         }
     }
     let pub_key = reader.read_string()?;
-    EcDsaPublicKey::from_bytes(curve, &pub_key)
+    EcDsaPublicKey::from_point_bytes(curve, &pub_key)
+}
+
+pub(crate) fn decode_ecdsa_sk_pubkey(keyblob: &[u8]) -> OsshResult<EcDsaSkPublicKey> {
+    let mut reader = io::Cursor::new(keyblob);
+    if reader.read_utf8()? != ECDSA_SK_NAME {
+        return Err(ErrorKind::TypeNotMatch.into());
+    }
+    if EcCurve::from_str(&reader.read_utf8()?)? != EcCurve::Nistp256 {
+        return Err(ErrorKind::TypeNotMatch.into());
+    }
+
+    let pub_key = reader.read_string()?;
+    let application = reader.read_utf8()?;
+    EcDsaSkPublicKey::new(&pub_key, &application)
 }
 
 pub(crate) fn decode_ed25519_pubkey(keyblob: &[u8]) -> OsshResult<Ed25519PublicKey> {
@@ -100,7 +231,41 @@ pub(crate) fn decode_ed25519_pubkey(keyblob: &[u8]) -> OsshResult<Ed25519PublicK
     )?)
 }
 
+pub(crate) fn decode_ed25519_sk_pubkey(keyblob: &[u8]) -> OsshResult<Ed25519SkPublicKey> {
+    let mut reader = io::Cursor::new(keyblob);
+    if reader.read_utf8()? != ED25519_SK_NAME {
+        return Err(ErrorKind::TypeNotMatch.into());
+    }
+
+    let pub_key = reader.read_string()?;
+    if pub_key.len() != PUBLIC_KEY_LENGTH {
+        return Err(ErrorKind::InvalidKeySize.into());
+    }
+    let application = reader.read_utf8()?;
+
+    Ok(Ed25519SkPublicKey::new(
+        pub_key.as_slice().try_into().unwrap(),
+        &application,
+    )?)
+}
+
+pub(crate) fn decode_xmss_pubkey(keyblob: &[u8]) -> OsshResult<XmssPublicKey> {
+    let mut reader = io::Cursor::new(keyblob);
+    if reader.read_utf8()? != XMSS_NAME {
+        return Err(ErrorKind::TypeNotMatch.into());
+    }
+
+    let params = reader.read_utf8()?;
+    let pub_key = reader.read_string()?;
+    XmssPublicKey::new(&params, &pub_key)
+}
+
+/// Serialize the openssh public key line, as in the `.pub` files and `authorized_keys`
+///
+/// Returns [`ErrorKind::InvalidArgument`](../../error/enum.ErrorKind.html#variant.InvalidArgument)
+/// if the comment contains a line break, which would inject another line into the file.
 pub fn serialize_ossh_pubkey(key: &dyn PublicParts, comment: &str) -> OsshResult<String> {
+    check_comment(comment.as_bytes())?;
     let mut keystr = String::new();
     write!(
         &mut keystr,
@@ -114,6 +279,32 @@ pub fn serialize_ossh_pubkey(key: &dyn PublicParts, comment: &str) -> OsshResult
     Ok(keystr)
 }
 
+/// Write the openssh public key line, followed by a newline, as in the `.pub` files
+///
+/// The base64 data is encoded directly into the writer.
+/// The comment is checked like [`serialize_ossh_pubkey()`](fn.serialize_ossh_pubkey.html) before anything is written.
+pub fn write_ossh_pubkey<W: io::Write + ?Sized>(
+    w: &mut W,
+    key: &dyn PublicParts,
+    comment: &[u8],
+) -> OsshResult<()> {
+    check_comment(comment)?;
+    let blob = key.blob()?;
+    w.write_all(key.keyname().as_bytes())?;
+    w.write_all(b" ")?;
+    {
+        let mut encoder = base64::write::EncoderWriter::new(&mut *w, &BASE64_STANDARD);
+        io::Write::write_all(&mut encoder, &blob)?;
+        encoder.finish()?;
+    }
+    if !comment.is_empty() {
+        w.write_all(b" ")?;
+        w.write_all(comment)?;
+    }
+    w.write_all(b"\n")?;
+    Ok(())
+}
+
 pub(crate) fn encode_rsa_pubkey<T: HasPublic + HasParams>(key: &RsaRef<T>) -> OsshResult<Vec<u8>> {
     let mut buf = io::Cursor::new(Vec::new());
 
@@ -158,6 +349,23 @@ pub(crate) fn encode_ecdsa_pubkey<T: HasPublic + HasParams>(
     Ok(buf.into_inner())
 }
 
+pub(crate) fn encode_ecdsa_sk_pubkey(key: &EcDsaSkPublicKey) -> OsshResult<Vec<u8>> {
+    let mut buf = io::Cursor::new(Vec::new());
+    let mut bn_ctx = BigNumContext::new()?;
+    let ec = key.ecdsa_key().ossl_ec();
+
+    buf.write_utf8(ECDSA_SK_NAME)?;
+    buf.write_utf8(EcCurve::Nistp256.ident())?;
+    buf.write_string(&ec.public_key().to_bytes(
+        ec.group(),
+        PointConversionForm::UNCOMPRESSED,
+        &mut bn_ctx,
+    )?)?;
+    buf.write_utf8(key.application())?;
+
+    Ok(buf.into_inner())
+}
+
 pub(crate) fn encode_ed25519_pubkey(pub_key: &Ed25519PubKey) -> OsshResult<Vec<u8>> {
     let mut buf = io::Cursor::new(Vec::new());
 
@@ -167,6 +375,26 @@ pub(crate) fn encode_ed25519_pubkey(pub_key: &Ed25519PubKey) -> OsshResult<Vec<u
     Ok(buf.into_inner())
 }
 
+pub(crate) fn encode_ed25519_sk_pubkey(key: &Ed25519SkPublicKey) -> OsshResult<Vec<u8>> {
+    let mut buf = io::Cursor::new(Vec::new());
+
+    buf.write_utf8(ED25519_SK_NAME)?;
+    buf.write_string(key.ed25519_key().as_bytes())?;
+    buf.write_utf8(key.application())?;
+
+    Ok(buf.into_inner())
+}
+
+pub(crate) fn encode_xmss_pubkey(key: &XmssPublicKey) -> OsshResult<Vec<u8>> {
+    let mut buf = io::Cursor::new(Vec::new());
+
+    buf.write_utf8(XMSS_NAME)?;
+    buf.write_utf8(key.params())?;
+    buf.write_string(key.raw_key())?;
+
+    Ok(buf.into_inner())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1,14 +1,19 @@
+#[cfg(feature = "experimental")]
 use crate::cipher::*;
 use crate::error::*;
 use crate::keys::{rsa::*, *};
+#[cfg(feature = "experimental")]
 use digest::DynDigest;
 use openssl::{
     pkey::{PKey, Public},
     rsa::Rsa,
 };
+#[cfg(feature = "experimental")]
 use pem::Pem as PemBlock;
-use zeroize::Zeroize;
+#[cfg(feature = "experimental")]
+use zeroize::{Zeroize, Zeroizing};
 
+#[cfg(feature = "experimental")]
 const MAX_KEY_LEN: usize = 64;
 
 //TODO: Not to depend on openssl to parse pem file in the future
@@ -17,7 +22,8 @@ pub fn parse_pem_privkey(pem: &[u8], passphrase: Option<&str>) -> OsshResult<Key
         PKey::private_key_from_pem_passphrase(pem, passphrase.as_bytes())
             .map_err(|_| ErrorKind::IncorrectPass)?
     } else {
-        PKey::private_key_from_pem(pem)?
+        // Provide an empty passphrase to prevent OpenSSL from prompting on the terminal
+        PKey::private_key_from_pem_callback(pem, |_| Ok(0))?
     };
 
     KeyPair::from_ossl_pkey(&pkey)
@@ -42,6 +48,9 @@ pub fn stringify_pem_privkey(keypair: &KeyPair, passphrase: Option<&str>) -> Oss
             KeyPairType::ED25519(key) => key
                 .ossl_pkey()?
                 .private_key_to_pem_pkcs8_passphrase(cipher, passphrase)?,
+            KeyPairType::ECDSA_SK(_) | KeyPairType::ED25519_SK(_) => {
+                return Err(ErrorKind::UnsupportType.into())
+            }
         }
     } else {
         match &keypair.key {
@@ -49,6 +58,9 @@ pub fn stringify_pem_privkey(keypair: &KeyPair, passphrase: Option<&str>) -> Oss
             KeyPairType::DSA(key) => key.ossl_dsa().private_key_to_pem()?,
             KeyPairType::ECDSA(key) => key.ossl_ec().private_key_to_pem()?,
             KeyPairType::ED25519(key) => key.ossl_pkey()?.private_key_to_pem_pkcs8()?,
+            KeyPairType::ECDSA_SK(_) | KeyPairType::ED25519_SK(_) => {
+                return Err(ErrorKind::UnsupportType.into())
+            }
         }
     };
 
@@ -60,6 +72,11 @@ pub fn parse_pem_pubkey(pem: &[u8]) -> OsshResult<PublicKey> {
         let rsa = Rsa::<Public>::public_key_from_pem_pkcs1(pem)?;
         let rsapubkey = RsaPublicKey::from_ossl_rsa(rsa, RsaSignature::SHA1)?;
         Ok(rsapubkey.into())
+    } else if pem.split(|&c| c == b'\n').next().map_or(false, |line| {
+        line.windows(16).any(|w| w == b"PRIVATE KEY-----")
+    }) {
+        // OpenSSL tries to decode (and prompt for the passphrase of) private keys here
+        Err(ErrorKind::InvalidPemFormat.into())
     } else {
         let pkey = PKey::public_key_from_pem(pem)?;
         Ok(PublicKey::from_ossl_pkey(&pkey)?)
@@ -72,11 +89,58 @@ pub fn stringify_pem_pubkey(pubkey: &PublicKey) -> OsshResult<String> {
         PublicKeyType::DSA(key) => key.ossl_pkey()?.public_key_to_pem()?,
         PublicKeyType::ECDSA(key) => key.ossl_pkey()?.public_key_to_pem()?,
         PublicKeyType::ED25519(key) => key.ossl_pkey()?.public_key_to_pem()?,
+        PublicKeyType::ECDSA_SK(_) | PublicKeyType::ED25519_SK(_) | PublicKeyType::XMSS(_) => {
+            return Err(ErrorKind::UnsupportType.into())
+        }
     };
 
     String::from_utf8(pem).map_err(|e| Error::with_error(ErrorKind::InvalidPemFormat, e))
 }
 
+/// The parsed `DEK-Info` header of a legacy encrypted OpenSSL PEM file
+#[cfg(feature = "experimental")]
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DekInfo {
+    cipher: Cipher,
+    iv: Vec<u8>,
+}
+
+#[cfg(feature = "experimental")]
+#[allow(dead_code)]
+impl DekInfo {
+    /// Parse the header value in the form of `<cipher>,<hex encoded iv>`
+    fn parse(header: &str) -> OsshResult<Self> {
+        let (algo, hexiv) = header
+            .trim()
+            .split_once(',')
+            .ok_or(ErrorKind::InvalidPemFormat)?;
+        let cipher = match algo {
+            "DES-EDE3-CBC" => Cipher::TDes_Cbc,
+            "AES-128-CBC" => Cipher::Aes128_Cbc,
+            "AES-192-CBC" => Cipher::Aes192_Cbc,
+            "AES-256-CBC" => Cipher::Aes256_Cbc,
+            _ => return Err(ErrorKind::UnsupportCipher.into()),
+        };
+        let hexiv = hexiv.trim();
+        if !hexiv.is_ascii() || hexiv.len() != cipher.iv_len() * 2 {
+            return Err(ErrorKind::InvalidPemFormat.into());
+        }
+        let iv = (0..hexiv.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hexiv[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|e| Error::with_error(ErrorKind::InvalidPemFormat, e))?;
+
+        Ok(DekInfo { cipher, iv })
+    }
+
+    /// The first 8 bytes of the IV are used as the salt of `EVP_BytesToKey()`
+    fn salt(&self) -> OsshResult<[u8; 8]> {
+        Ok(self.iv[..8].try_into()?)
+    }
+}
+
 /// Self experimental implementation for decrypting OpenSSL PEM format
 #[cfg(feature = "experimental")]
 #[allow(dead_code)]
@@ -94,44 +158,27 @@ fn pem_decrypt(pemblock: &PemBlock, passphrase: Option<&[u8]>) -> OsshResult<Vec
             return Err(ErrorKind::UnsupportType.into());
         }
     }
-    if encrypted {
-        let mut decrypted = None;
-        if let Some(header) = pemblock.headers().get("DEK-Info") {
-            let re = ::regex::Regex::new(
-                r"^(DES-CBC|DES-EDE3-CBC|AES-128-CBC|AES-192-CBC|AES-256-CBC),([0-9a-fA-F]+)$",
-            )
-            .expect("regexp should compile");
-            if let Some(caps) = re.captures(header) {
-                let algo = caps.get(1).map_or("", |m| m.as_str());
-                let iv = caps.get(2).map_or("", |m| m.as_str()).as_bytes();
-                if let Some(passphrase) = passphrase {
-                    let ciph = match algo {
-                        "DES-CBC" => return Err(ErrorKind::UnsupportCipher.into()),
-                        "DES-EDE3-CBC" => Cipher::TDes_Cbc,
-                        "AES-128-CBC" => Cipher::Aes128_Cbc,
-                        "AES-192-CBC" => Cipher::Aes192_Cbc,
-                        "AES-256-CBC" => Cipher::Aes256_Cbc,
-                        _ => return Err(ErrorKind::UnsupportCipher.into()),
-                    };
-                    let key = openssl_kdf(
-                        passphrase,
-                        &iv.try_into()?,
-                        &mut md5::Md5::default(),
-                        ciph.key_len(),
-                        1,
-                    )?;
-                    decrypted = Some(ciph.decrypt(pemblock.contents(), &key, iv)?);
-                } else {
-                    return Err(ErrorKind::IncorrectPass.into());
-                };
-            }
-        }
-        if let Some(data) = decrypted {
-            return Ok(data);
-        }
-        return Err(ErrorKind::InvalidPemFormat.into());
+    if !encrypted {
+        return Ok(pemblock.contents().to_vec());
     }
-    return Ok(pemblock.contents().to_vec());
+
+    let header = pemblock
+        .headers()
+        .get("DEK-Info")
+        .ok_or(ErrorKind::InvalidPemFormat)?;
+    let dekinfo = DekInfo::parse(header)?;
+    let passphrase = passphrase.ok_or(ErrorKind::IncorrectPass)?;
+    let key = Zeroizing::new(openssl_kdf(
+        passphrase,
+        &dekinfo.salt()?,
+        &mut md5::Md5::default(),
+        dekinfo.cipher.key_len(),
+        1,
+    )?);
+    dekinfo
+        .cipher
+        .decrypt(pemblock.contents(), &key, &dekinfo.iv)
+        .map_err(|_| ErrorKind::IncorrectPass.into())
 }
 
 /// Self experimental implementation for OpenSSL kdf
@@ -180,3 +227,45 @@ fn openssl_kdf(
     dig.zeroize();
     Ok(key)
 }
+
+#[cfg(feature = "experimental")]
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dekinfo_parse() {
+        let dekinfo = DekInfo::parse("DES-EDE3-CBC,5889904461A529B7").unwrap();
+        assert_eq!(dekinfo.cipher, Cipher::TDes_Cbc);
+        assert_eq!(dekinfo.iv, [0x58, 0x89, 0x90, 0x44, 0x61, 0xa5, 0x29, 0xb7]);
+
+        let dekinfo = DekInfo::parse("AES-256-CBC,A3644FFE7B8CE31E446652D5DEED8062").unwrap();
+        assert_eq!(dekinfo.cipher, Cipher::Aes256_Cbc);
+        assert_eq!(dekinfo.iv.len(), 16);
+    }
+
+    #[test]
+    fn dekinfo_parse_invalid() {
+        let kind = |s| DekInfo::parse(s).unwrap_err().kind();
+        assert_eq!(kind("AES-128-CBC"), ErrorKind::InvalidPemFormat);
+        assert_eq!(kind("AES-128-CBC,0C039799"), ErrorKind::InvalidPemFormat);
+        assert_eq!(
+            kind("AES-128-CBC,0C03979933221248B06495E14F1FE3ZZ"),
+            ErrorKind::InvalidPemFormat
+        );
+        assert_eq!(kind("DES-CBC,5889904461A529B7"), ErrorKind::UnsupportCipher);
+    }
+
+    #[test]
+    fn pem_decrypt_3des() {
+        let keyfile = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/pem_rsa_enc_3des"
+        ))
+        .unwrap();
+        let pemblock = ::pem::parse(keyfile).unwrap();
+        let der = pem_decrypt(&pemblock, Some(b"12345678")).unwrap();
+        let rsa = Rsa::private_key_from_der(&der).unwrap();
+        assert_eq!(rsa.size(), 256);
+    }
+}
@@ -0,0 +1,572 @@
+use crate::cipher::Cipher;
+use crate::error::*;
+use crate::format::check_comment;
+use crate::format::ossh_pubkey::decode_rsa_pubkey;
+use crate::keys::{
+    dsa::*, ecdsa::*, ed25519::*, rsa::*, Key, KeyPair, KeyPairType, ParseOptions, PublicParts,
+};
+use crate::mac::{hmac_sha1, hmac_sha256};
+use crate::sshbuf::{read_secret_mpint, SshReadExt, SshWriteExt};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::prelude::*;
+use openssl::dsa::Dsa;
+use openssl::memcmp;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use sha1::{Digest, Sha1};
+use std::fmt::Write;
+use std::io::Cursor;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+const PUTTY_HEADER_PREFIX: &str = "PuTTY-User-Key-File-";
+const ENCRYPTION_NONE: &str = "none";
+const ENCRYPTION_AES256_CBC: &str = "aes256-cbc";
+const V2_MAC_KEY_PREFIX: &[u8] = b"putty-private-key-file-mac-key";
+// Refuse the crafted files which would make Argon2 allocate too much memory (in KiB)
+// or run for too long, PuTTYgen itself uses far smaller parameters
+const MAX_ARGON2_MEMORY: u32 = 256 * 1024;
+const MAX_ARGON2_PASSES: u32 = 1024;
+const MAX_ARGON2_PARALLELISM: u32 = 16;
+// The limit of memory (in KiB) multiplied by passes, i.e. about the number of blocks computed
+const MAX_ARGON2_COST: u64 = 4 * 1024 * 1024;
+// The Argon2 parameters used for writing the encrypted files of version 3,
+// the memory and parallelism are the defaults of PuTTYgen
+const DEFAULT_ARGON2_MEMORY: u32 = 8192;
+const DEFAULT_ARGON2_PASSES: u32 = 8;
+const DEFAULT_ARGON2_PARALLELISM: u32 = 1;
+const ARGON2_SALT_LEN: usize = 16;
+const BASE64_LINE_LEN: usize = 64;
+
+/// The version of the PuTTY private key (`.ppk`) file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PuttyVersion {
+    /// Version 2, derives the keys by SHA-1 and uses HMAC-SHA1, readable by PuTTY 0.52 and later
+    V2,
+    /// Version 3, derives the keys by Argon2 and uses HMAC-SHA256, readable by PuTTY 0.75 and later
+    V3,
+}
+
+/// Check if the data looks like a PuTTY private key (`.ppk`) file
+pub fn is_putty_privkey(keydata: &[u8]) -> bool {
+    let start = keydata
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(keydata.len());
+    keydata[start..].starts_with(PUTTY_HEADER_PREFIX.as_bytes())
+}
+
+/// Decode the PuTTY private key (`.ppk`) file of version 2 or 3
+///
+/// The RSA, DSA, EcDSA and Ed25519 keys are supported.
+/// The key comment is read from the `Comment` field.
+///
+/// The encrypted keys use AES-256-CBC, with the key derived by SHA-1 in version 2,
+/// or by Argon2 (`Argon2id`, `Argon2i` or `Argon2d`) in version 3.
+/// The Argon2 parameters over 256 MiB of memory, 1024 passes, 16 lanes,
+/// or 4 GiB of memory multiplied by the passes are rejected with
+/// [`ErrorKind::InvalidKeyFormat`](../../error/enum.ErrorKind.html#variant.InvalidKeyFormat),
+/// so a crafted file can't make the key derivation exhaust the memory or run for hours.
+/// The `Private-MAC` field is checked by HMAC-SHA1 in version 2 and HMAC-SHA256 in version 3.
+/// If it doesn't match, [`ErrorKind::IncorrectPass`](../../error/enum.ErrorKind.html#variant.IncorrectPass)
+/// is returned for the encrypted keys,
+/// and [`ErrorKind::InvalidKeyFormat`](../../error/enum.ErrorKind.html#variant.InvalidKeyFormat) otherwise.
+pub fn decode_putty_priv(keydata: &[u8], passphrase: Option<&[u8]>) -> OsshResult<KeyPair> {
+    decode_putty_priv_with_options(keydata, passphrase, &ParseOptions::default())
+}
+
+// Decode the private key, applying the limits to the public key before the key derivation
+pub(crate) fn decode_putty_priv_with_options(
+    keydata: &[u8],
+    passphrase: Option<&[u8]>,
+    options: &ParseOptions,
+) -> OsshResult<KeyPair> {
+    let file = PuttyFile::parse(keydata)?;
+    if file.algorithm == RSA_NAME {
+        decode_rsa_pubkey(&file.public, options.max_rsa_bits)?;
+    }
+    let encrypted = match file.encryption.as_str() {
+        ENCRYPTION_NONE => false,
+        ENCRYPTION_AES256_CBC => true,
+        _ => return Err(ErrorKind::UnsupportCipher.into()),
+    };
+    let passphrase = passphrase.unwrap_or_default();
+    if encrypted && passphrase.is_empty() {
+        return Err(ErrorKind::IncorrectPass.into());
+    }
+    let passphrase = if encrypted { passphrase } else { &[] };
+
+    let (cipher_key, mac_key) = match file.version {
+        2 => derive_v2(passphrase),
+        _ => match &file.kdf {
+            Some(kdf) if encrypted => derive_v3(kdf, passphrase)?,
+            None if !encrypted => (Zeroizing::new(Vec::new()), Zeroizing::new(Vec::new())),
+            _ => return Err(ErrorKind::InvalidKeyFormat.into()),
+        },
+    };
+
+    let private = if encrypted {
+        let cipher = Cipher::Aes256_Cbc;
+        if file.private.is_empty() || file.private.len() % cipher.block_size() != 0 {
+            return Err(ErrorKind::InvalidKeyFormat.into());
+        }
+        let (key, iv) = cipher_key.split_at(cipher.key_len());
+        let mut buf = Zeroizing::new(vec![0u8; cipher.calc_buffer_len(file.private.len())]);
+        let n = cipher.decrypt_unpadded_to(&mut buf, &file.private, key, iv)?;
+        buf.truncate(n);
+        buf
+    } else {
+        file.private.clone()
+    };
+
+    // The MAC covers the algorithm, the encryption, the comment, and both of the key blobs
+    let mut macdata = Zeroizing::new(Vec::new());
+    macdata.write_utf8(&file.algorithm)?;
+    macdata.write_utf8(&file.encryption)?;
+    macdata.write_string(&file.comment)?;
+    macdata.write_string(&file.public)?;
+    macdata.write_string(&private)?;
+    let mac = match file.version {
+        2 => hmac_sha1(&mac_key, &macdata)?,
+        _ => hmac_sha256(&mac_key, &macdata)?,
+    };
+    if mac.len() != file.mac.len() || !memcmp::eq(&mac, &file.mac) {
+        return Err(if encrypted {
+            ErrorKind::IncorrectPass.into()
+        } else {
+            ErrorKind::InvalidKeyFormat.into()
+        });
+    }
+
+    let mut keypair = decode_key(&file.algorithm, &file.public, &private)?;
+    keypair.set_comment_bytes(&file.comment);
+    Ok(keypair)
+}
+
+/// Serialize the keypair to the PuTTY private key (`.ppk`) file of the given version
+///
+/// If the passphrase is given and not empty, the private key will be encrypted by AES-256-CBC.
+/// The version 3 files derive the key by `Argon2id` with 8 MiB of memory, 8 passes and 1 lane.
+///
+/// Returns [`ErrorKind::InvalidArgument`](../../error/enum.ErrorKind.html#variant.InvalidArgument)
+/// if the comment contains a line break, which can't be stored in the `Comment` field.
+pub fn serialize_putty_priv(
+    key: &KeyPair,
+    passphrase: Option<&[u8]>,
+    version: PuttyVersion,
+) -> OsshResult<String> {
+    let comment = key.comment_bytes();
+    check_comment(comment)?;
+    let passphrase = passphrase.unwrap_or_default();
+    let encrypted = !passphrase.is_empty();
+    let encryption = if encrypted {
+        ENCRYPTION_AES256_CBC
+    } else {
+        ENCRYPTION_NONE
+    };
+    let public = key.blob()?;
+    let algorithm = Cursor::new(&public).read_utf8()?;
+
+    let mut rng = StdRng::from_entropy();
+    let kdf = if version == PuttyVersion::V3 && encrypted {
+        let mut salt = vec![0u8; ARGON2_SALT_LEN];
+        rng.fill_bytes(&mut salt);
+        Some(Argon2Params {
+            variant: Algorithm::Argon2id,
+            memory: DEFAULT_ARGON2_MEMORY,
+            passes: DEFAULT_ARGON2_PASSES,
+            parallelism: DEFAULT_ARGON2_PARALLELISM,
+            salt,
+        })
+    } else {
+        None
+    };
+    let (cipher_key, mac_key) = match (version, &kdf) {
+        (PuttyVersion::V2, _) => derive_v2(passphrase),
+        (PuttyVersion::V3, Some(kdf)) => derive_v3(kdf, passphrase)?,
+        (PuttyVersion::V3, None) => (Zeroizing::new(Vec::new()), Zeroizing::new(Vec::new())),
+    };
+
+    let mut private = Zeroizing::new(Vec::new());
+    encode_key(key, &mut private)?;
+    if encrypted {
+        // Pad the private blob to the cipher block size, the padding is covered by the MAC
+        let cipher = Cipher::Aes256_Cbc;
+        let len = private.len();
+        let padded = (len + cipher.block_size() - 1) / cipher.block_size() * cipher.block_size();
+        private.resize(padded, 0);
+        rng.fill_bytes(&mut private[len..]);
+    }
+
+    let mut macdata = Zeroizing::new(Vec::new());
+    macdata.write_utf8(&algorithm)?;
+    macdata.write_utf8(encryption)?;
+    macdata.write_string(comment)?;
+    macdata.write_string(&public)?;
+    macdata.write_string(&private)?;
+    let mac = match version {
+        PuttyVersion::V2 => hmac_sha1(&mac_key, &macdata)?,
+        PuttyVersion::V3 => hmac_sha256(&mac_key, &macdata)?,
+    };
+
+    let private = if encrypted {
+        let cipher = Cipher::Aes256_Cbc;
+        let (key, iv) = cipher_key.split_at(cipher.key_len());
+        Zeroizing::new(cipher.encrypt_unpadded(&private, key, iv)?)
+    } else {
+        private
+    };
+
+    let mut keystr = String::new();
+    let number = match version {
+        PuttyVersion::V2 => 2,
+        PuttyVersion::V3 => 3,
+    };
+    // Writing to a String never fails
+    let _ = writeln!(keystr, "{}{}: {}", PUTTY_HEADER_PREFIX, number, algorithm);
+    let _ = writeln!(keystr, "Encryption: {}", encryption);
+    let _ = writeln!(keystr, "Comment: {}", String::from_utf8_lossy(comment));
+    write_base64_lines(&mut keystr, "Public-Lines", &public);
+    if let Some(kdf) = &kdf {
+        let _ = writeln!(keystr, "Key-Derivation: Argon2id");
+        let _ = writeln!(keystr, "Argon2-Memory: {}", kdf.memory);
+        let _ = writeln!(keystr, "Argon2-Passes: {}", kdf.passes);
+        let _ = writeln!(keystr, "Argon2-Parallelism: {}", kdf.parallelism);
+        let _ = writeln!(keystr, "Argon2-Salt: {}", to_hex(&kdf.salt));
+    }
+    write_base64_lines(&mut keystr, "Private-Lines", &private);
+    let _ = writeln!(keystr, "Private-MAC: {}", to_hex(&mac));
+    Ok(keystr)
+}
+
+// The fields of the key file
+struct PuttyFile {
+    version: u32,
+    algorithm: String,
+    encryption: String,
+    comment: Vec<u8>,
+    public: Vec<u8>,
+    kdf: Option<Argon2Params>,
+    private: Zeroizing<Vec<u8>>,
+    mac: Vec<u8>,
+}
+
+struct Argon2Params {
+    variant: Algorithm,
+    memory: u32,
+    passes: u32,
+    parallelism: u32,
+    salt: Vec<u8>,
+}
+
+impl Argon2Params {
+    // Reject the parameters of crafted files before running Argon2,
+    // since the MAC can only be checked after the key derivation
+    fn check_limits(&self) -> OsshResult<()> {
+        if self.memory > MAX_ARGON2_MEMORY
+            || self.passes > MAX_ARGON2_PASSES
+            || self.parallelism > MAX_ARGON2_PARALLELISM
+            || u64::from(self.memory) * u64::from(self.passes) > MAX_ARGON2_COST
+        {
+            return Err(ErrorKind::InvalidKeyFormat.into());
+        }
+        Ok(())
+    }
+}
+
+impl PuttyFile {
+    fn parse(keydata: &[u8]) -> OsshResult<Self> {
+        let mut lines = keydata
+            .split(|&b| b == b'\n')
+            .map(|l| l.strip_suffix(b"\r").unwrap_or(l))
+            .skip_while(|l| l.iter().all(u8::is_ascii_whitespace));
+
+        let (header, algorithm) = split_field(lines.next())?;
+        let header = utf8(header)?;
+        let version = match header.strip_prefix(PUTTY_HEADER_PREFIX) {
+            Some("2") => 2,
+            Some("3") => 3,
+            _ => return Err(Error::with_failure(ErrorKind::UnsupportType, header)),
+        };
+        let algorithm = utf8(algorithm)?.to_owned();
+        let encryption = utf8(expect_field(&mut lines, "Encryption")?)?.to_owned();
+        let comment = expect_field(&mut lines, "Comment")?.to_vec();
+        let public = take_lines(&mut lines, "Public-Lines")?;
+
+        let mut field = split_field(lines.next())?;
+        let kdf = if version == 3 && field.0 == b"Key-Derivation" {
+            let variant = match field.1 {
+                b"Argon2id" => Algorithm::Argon2id,
+                b"Argon2i" => Algorithm::Argon2i,
+                b"Argon2d" => Algorithm::Argon2d,
+                _ => return Err(ErrorKind::UnsupportCipher.into()),
+            };
+            let mut number = |name| -> OsshResult<u32> {
+                utf8(expect_field(&mut lines, name)?)?
+                    .parse()
+                    .map_err(|_| Error::from_kind(ErrorKind::InvalidKeyFormat))
+            };
+            let memory = number("Argon2-Memory")?;
+            let passes = number("Argon2-Passes")?;
+            let parallelism = number("Argon2-Parallelism")?;
+            let salt = from_hex(expect_field(&mut lines, "Argon2-Salt")?)?;
+            field = split_field(lines.next())?;
+            let kdf = Argon2Params {
+                variant,
+                memory,
+                passes,
+                parallelism,
+                salt,
+            };
+            kdf.check_limits()?;
+            Some(kdf)
+        } else {
+            None
+        };
+
+        if field.0 != b"Private-Lines" {
+            return Err(ErrorKind::InvalidKeyFormat.into());
+        }
+        let private = Zeroizing::new(read_base64_lines(&mut lines, field.1)?);
+        let mac = from_hex(expect_field(&mut lines, "Private-MAC")?)?;
+        if lines.any(|l| !l.iter().all(u8::is_ascii_whitespace)) {
+            return Err(ErrorKind::InvalidKeyFormat.into());
+        }
+
+        Ok(PuttyFile {
+            version,
+            algorithm,
+            encryption,
+            comment,
+            public,
+            kdf,
+            private,
+            mac,
+        })
+    }
+}
+
+fn utf8(data: &[u8]) -> OsshResult<&str> {
+    std::str::from_utf8(data).map_err(|_| Error::from_kind(ErrorKind::InvalidKeyFormat))
+}
+
+// Split the `Name: value` line
+fn split_field(line: Option<&[u8]>) -> OsshResult<(&[u8], &[u8])> {
+    let line = line.ok_or(ErrorKind::InvalidKeyFormat)?;
+    let pos = line
+        .windows(2)
+        .position(|w| w == b": ")
+        .ok_or(ErrorKind::InvalidKeyFormat)?;
+    Ok((&line[..pos], &line[pos + 2..]))
+}
+
+fn expect_field<'a, I: Iterator<Item = &'a [u8]>>(
+    lines: &mut I,
+    name: &str,
+) -> OsshResult<&'a [u8]> {
+    let (field, value) = split_field(lines.next())?;
+    if field != name.as_bytes() {
+        return Err(ErrorKind::InvalidKeyFormat.into());
+    }
+    Ok(value)
+}
+
+fn take_lines<'a, I: Iterator<Item = &'a [u8]>>(lines: &mut I, name: &str) -> OsshResult<Vec<u8>> {
+    let count = expect_field(lines, name)?;
+    read_base64_lines(lines, count)
+}
+
+fn read_base64_lines<'a, I: Iterator<Item = &'a [u8]>>(
+    lines: &mut I,
+    count: &[u8],
+) -> OsshResult<Vec<u8>> {
+    let count: usize = utf8(count)?
+        .parse()
+        .map_err(|_| Error::from_kind(ErrorKind::InvalidKeyFormat))?;
+    let mut b64 = Zeroizing::new(Vec::new());
+    for _ in 0..count {
+        b64.extend_from_slice(lines.next().ok_or(ErrorKind::InvalidKeyFormat)?);
+    }
+    Ok(BASE64_STANDARD.decode(&*b64)?)
+}
+
+fn from_hex(hex: &[u8]) -> OsshResult<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return Err(ErrorKind::InvalidKeyFormat.into());
+    }
+    hex.chunks_exact(2)
+        .map(|pair| {
+            utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| Error::from_kind(ErrorKind::InvalidKeyFormat))
+        })
+        .collect()
+}
+
+fn write_base64_lines(keystr: &mut String, name: &str, data: &[u8]) {
+    let b64 = Zeroizing::new(BASE64_STANDARD.encode(data));
+    let lines: Vec<&[u8]> = b64.as_bytes().chunks(BASE64_LINE_LEN).collect();
+    let _ = writeln!(keystr, "{}: {}", name, lines.len());
+    for line in lines {
+        // The base64 output is always ASCII
+        keystr.push_str(std::str::from_utf8(line).unwrap());
+        keystr.push('\n');
+    }
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// The AES-256-CBC key with IV, and the MAC key
+type DerivedKeys = (Zeroizing<Vec<u8>>, Zeroizing<Vec<u8>>);
+
+fn derive_v2(passphrase: &[u8]) -> DerivedKeys {
+    let mut cipher_key = Zeroizing::new(Vec::with_capacity(48));
+    for counter in 0u32..2 {
+        let digest = Sha1::new()
+            .chain_update(counter.to_be_bytes())
+            .chain_update(passphrase)
+            .finalize();
+        cipher_key.extend_from_slice(&digest);
+    }
+    // 32 bytes of the key and 16 bytes of zero IV
+    cipher_key.truncate(32);
+    cipher_key.resize(48, 0);
+    let mac_key = Sha1::new()
+        .chain_update(V2_MAC_KEY_PREFIX)
+        .chain_update(passphrase)
+        .finalize();
+    (cipher_key, Zeroizing::new(mac_key.to_vec()))
+}
+
+fn derive_v3(kdf: &Argon2Params, passphrase: &[u8]) -> OsshResult<DerivedKeys> {
+    kdf.check_limits()?;
+    // 32 bytes of the key, 16 bytes of the IV and 32 bytes of the MAC key
+    let mut output = Zeroizing::new(vec![0u8; 80]);
+    let params = Params::new(kdf.memory, kdf.passes, kdf.parallelism, None)
+        .map_err(|_| Error::from_kind(ErrorKind::InvalidKeyFormat))?;
+    Argon2::new(kdf.variant, Version::V0x13, params)
+        .hash_password_into(passphrase, &kdf.salt, &mut output)
+        .map_err(|_| Error::from_kind(ErrorKind::InvalidKeyFormat))?;
+    let mac_key = Zeroizing::new(output[48..].to_vec());
+    output.truncate(48);
+    Ok((output, mac_key))
+}
+
+#[allow(clippy::many_single_char_names)]
+fn decode_key(algorithm: &str, public: &[u8], private: &[u8]) -> OsshResult<KeyPair> {
+    let mut pubreader = Cursor::new(public);
+    let mut privreader = Cursor::new(private);
+    if pubreader.read_utf8()? != algorithm {
+        return Err(ErrorKind::TypeNotMatch.into());
+    }
+    let keypair = match algorithm {
+        RSA_NAME => {
+            let e = pubreader.read_mpint()?;
+            let n = pubreader.read_mpint()?;
+            let d = read_secret_mpint(&mut privreader)?;
+            let p = read_secret_mpint(&mut privreader)?;
+            let q = read_secret_mpint(&mut privreader)?;
+            let iqmp = read_secret_mpint(&mut privreader)?;
+            RsaKeyPair::from_secret_parts(n, e, d, p, q, iqmp, RsaSignature::SHA1)?.into()
+        }
+        DSA_NAME => {
+            let p = pubreader.read_mpint()?;
+            let q = pubreader.read_mpint()?;
+            let g = pubreader.read_mpint()?;
+            let y = pubreader.read_mpint()?;
+            let x = read_secret_mpint(&mut privreader)?;
+            DsaKeyPair::from_ossl_dsa(Dsa::from_private_components(p, q, g, x.into_inner(), y)?)
+                .into()
+        }
+        NIST_P256_NAME | NIST_P384_NAME | NIST_P521_NAME => {
+            let curve = EcCurve::from_name(algorithm)?;
+            if EcCurve::from_str(&pubreader.read_utf8()?)? != curve {
+                return Err(ErrorKind::TypeNotMatch.into());
+            }
+            let point = pubreader.read_string()?;
+            let privkey = read_secret_mpint(&mut privreader)?;
+            EcDsaKeyPair::from_bytes(curve, &point, &privkey)?.into()
+        }
+        ED25519_NAME => {
+            let pk = pubreader.read_string()?;
+            // The secret key is stored as a little-endian integer without the leading zeros
+            let seed = Zeroizing::new(privreader.read_string()?);
+            if seed.len() > 32 {
+                return Err(ErrorKind::InvalidKey.into());
+            }
+            let mut sk = Zeroizing::new(vec![0u8; 64]);
+            sk[..seed.len()].copy_from_slice(&seed);
+            sk[32..].copy_from_slice(pk.get(..32).ok_or(ErrorKind::InvalidLength)?);
+            Ed25519KeyPair::from_bytes(&pk, &sk)?.into()
+        }
+        _ => return Err(Error::with_failure(ErrorKind::UnsupportType, algorithm)),
+    };
+    Ok(keypair)
+}
+
+fn encode_key(key: &KeyPair, buf: &mut Vec<u8>) -> OsshResult<()> {
+    match &key.key {
+        KeyPairType::RSA(rsa) => {
+            let inner = rsa.ossl_rsa();
+            buf.write_mpint(inner.d())?;
+            buf.write_mpint(inner.p().ok_or(ErrorKind::InvalidKey)?)?;
+            buf.write_mpint(inner.q().ok_or(ErrorKind::InvalidKey)?)?;
+            buf.write_mpint(inner.iqmp().ok_or(ErrorKind::InvalidKey)?)?;
+        }
+        KeyPairType::DSA(dsa) => {
+            buf.write_mpint(dsa.ossl_dsa().priv_key())?;
+        }
+        KeyPairType::ECDSA(ecdsa) => {
+            buf.write_mpint(ecdsa.ossl_ec().private_key())?;
+        }
+        KeyPairType::ED25519(ed25519) => {
+            // Stored as a little-endian integer without the leading zeros, like it is read
+            let seed = Zeroizing::new(ed25519.key.to_bytes());
+            let len = seed.iter().rposition(|&b| b != 0).map_or(0, |pos| pos + 1);
+            buf.write_string(&seed[..len])?;
+        }
+        KeyPairType::ECDSA_SK(_) | KeyPairType::ED25519_SK(_) => {
+            return Err(Error::with_failure(ErrorKind::UnsupportType, key.keyname()))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use openssl::bn::BigNum;
+
+    fn rsa_blobs(n: u32, d: u32, p: u32, q: u32) -> (Vec<u8>, Vec<u8>) {
+        let mpint = |buf: &mut Vec<u8>, value: u32| {
+            buf.write_mpint(&BigNum::from_u32(value).unwrap()).unwrap();
+        };
+        let mut public = Vec::new();
+        public.write_utf8(RSA_NAME).unwrap();
+        mpint(&mut public, 3);
+        mpint(&mut public, n);
+        let mut private = Vec::new();
+        for value in [d, p, q, 1] {
+            mpint(&mut private, value);
+        }
+        (public, private)
+    }
+
+    #[test]
+    fn rsa_invalid_primes() {
+        // The prime 1 and the zero modulus, which would make computing the CRT exponents divide by zero
+        for (n, d, p, q) in [(15, 3, 1, 15), (0, 3, 3, 5), (0, 1, 0, 0)] {
+            let (public, private) = rsa_blobs(n, d, p, q);
+            assert_eq!(
+                decode_key(RSA_NAME, &public, &private)
+                    .err()
+                    .unwrap()
+                    .kind(),
+                ErrorKind::InvalidKeyFormat
+            );
+        }
+    }
+}
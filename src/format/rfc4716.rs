@@ -0,0 +1,133 @@
+use crate::error::*;
+use crate::keys::PublicKey;
+use base64::prelude::*;
+use std::collections::BTreeMap;
+
+/// The first line of the RFC 4716 public key
+pub const BEGIN_MARKER: &str = "---- BEGIN SSH2 PUBLIC KEY ----";
+/// The last line of the RFC 4716 public key
+pub const END_MARKER: &str = "---- END SSH2 PUBLIC KEY ----";
+
+/// The public key parsed from the RFC 4716 (SSH2, used by Tectia) format, with its headers
+#[derive(Clone)]
+pub struct Rfc4716PublicKey {
+    /// The public key, using the `Comment` header as its comment
+    pub key: PublicKey,
+    /// The headers, e.g. `Subject`, `Comment` and the private `x-` headers
+    ///
+    /// The continuation lines are joined, the `\` escapes are resolved,
+    /// and the surrounding double quotes are removed.
+    /// The header tags are kept in their original case.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Rfc4716PublicKey {
+    /// Get the header value by the case-insensitive tag, as required by RFC 4716
+    pub fn header(&self, tag: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(tag))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Check if the string looks like a RFC 4716 public key
+pub fn is_rfc4716(keystr: &str) -> bool {
+    keystr.trim_start().starts_with(BEGIN_MARKER)
+}
+
+/// Parse the RFC 4716 public key with its headers
+pub fn parse_rfc4716_pubkey(keystr: &str) -> OsshResult<Rfc4716PublicKey> {
+    let mut lines = keystr
+        .lines()
+        .map(str::trim_end)
+        .skip_while(|l| l.is_empty());
+    if lines.next().map(str::trim_start) != Some(BEGIN_MARKER) {
+        return Err(ErrorKind::InvalidKeyFormat.into());
+    }
+
+    let mut headers = BTreeMap::new();
+    let mut keyb64 = String::new();
+    let mut folded: Option<String> = None;
+    let mut ended = false;
+    for line in lines.by_ref() {
+        if let Some(mut header) = folded.take() {
+            // Continuation of the previous header line
+            header.push_str(line);
+            folded = fold_header(header, &mut headers)?;
+        } else if line == END_MARKER {
+            ended = true;
+            break;
+        } else if keyb64.is_empty() && line.contains(':') {
+            folded = fold_header(line.to_owned(), &mut headers)?;
+        } else {
+            keyb64.push_str(line.trim_start());
+        }
+    }
+    if !ended || folded.is_some() || lines.any(|l| !l.trim().is_empty()) {
+        return Err(ErrorKind::InvalidKeyFormat.into());
+    }
+
+    let blob = BASE64_STANDARD.decode(keyb64)?;
+    let mut result = Rfc4716PublicKey {
+        key: PublicKey::from_blob(&blob)?,
+        headers,
+    };
+    if let Some(comment) = result.header("Comment").map(str::to_owned) {
+        *result.key.comment_mut() = comment;
+    }
+    Ok(result)
+}
+
+// Insert the header, or return it back if it continues on the next line
+fn fold_header(
+    mut line: String,
+    headers: &mut BTreeMap<String, String>,
+) -> OsshResult<Option<String>> {
+    if ends_with_continuation(&line) {
+        line.pop();
+        return Ok(Some(line));
+    }
+    let (tag, value) = line.split_once(':').ok_or(ErrorKind::InvalidKeyFormat)?;
+    if tag.is_empty() || tag.contains(char::is_whitespace) {
+        return Err(ErrorKind::InvalidKeyFormat.into());
+    }
+    headers.insert(tag.to_owned(), unescape(value.trim_start()));
+    Ok(None)
+}
+
+// A trailing backslash continues the line, unless it is escaped itself
+fn ends_with_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1
+}
+
+fn unescape(value: &str) -> String {
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .filter(|v| !ends_with_continuation(v))
+        .unwrap_or(value);
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.extend(chars.next()),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rfc4716_unescape() {
+        assert_eq!(unescape("\"quoted \\\"value\\\"\""), "quoted \"value\"");
+        assert_eq!(unescape("a\\\\b"), "a\\b");
+        assert_eq!(unescape("\"unbalanced"), "\"unbalanced");
+        assert!(ends_with_continuation("value\\"));
+        assert!(!ends_with_continuation("value\\\\"));
+    }
+}
@@ -0,0 +1,300 @@
+use crate::error::*;
+use crate::format::ossh_pubkey::blob_keyname;
+use crate::keys::cert::{CertType, Certificate};
+use crate::keys::rsa::{RsaSignature, RSA_NAME};
+use crate::keys::{FingerprintHash, KeyPair, PublicKey, PublicParts, VerifyOutcome};
+use crate::sshbuf::{SshReadExt, SshWriteExt};
+use base64::prelude::*;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The magic preamble of the SSHSIG blob and the signed data
+pub const SSHSIG_MAGIC: &[u8; 6] = b"SSHSIG";
+/// The version of the SSHSIG format
+pub const SSHSIG_VERSION: u32 = 1;
+/// The first line of the armored SSHSIG signature
+pub const BEGIN_MARKER: &str = "-----BEGIN SSH SIGNATURE-----";
+/// The last line of the armored SSHSIG signature
+pub const END_MARKER: &str = "-----END SSH SIGNATURE-----";
+
+const BASE64_LINE_LEN: usize = 70;
+// The suffix of the certificate key types
+const CERT_SUFFIX: &str = "-cert-v01@openssh.com";
+
+/// Sign the data as `ssh-keygen -Y sign` does, returning the armored SSHSIG signature
+///
+/// The namespace (e.g. `git` or `file`) is signed together with the hash of the data,
+/// so a signature made for one purpose can't be used for another.
+/// Only SHA256 and SHA512 hashes are allowed by the format.
+/// RSA keys always sign with `rsa-sha2-512`, as OpenSSH does.
+pub fn sign_sshsig(
+    key: &KeyPair,
+    data: &[u8],
+    namespace: &str,
+    hash: FingerprintHash,
+) -> OsshResult<String> {
+    if namespace.is_empty() {
+        return Err(Error::with_failure(
+            ErrorKind::InvalidArgument,
+            "The namespace is empty",
+        ));
+    }
+    let hashalg = hash_name(hash)?;
+    let signed = signed_data(namespace, hashalg, &hash.hash(data))?;
+    let signature = key.sign_with_alg(&signed, Some(RsaSignature::SHA2_512))?;
+
+    let mut blob = Vec::new();
+    blob.extend_from_slice(SSHSIG_MAGIC);
+    blob.write_uint32(SSHSIG_VERSION)?;
+    blob.write_string(&key.blob()?)?;
+    blob.write_utf8(namespace)?;
+    blob.write_string(&[])?; // Reserved
+    blob.write_utf8(hashalg)?;
+    blob.write_string(&signature)?;
+    Ok(armor(&blob))
+}
+
+/// Verify the armored SSHSIG signature made by `ssh-keygen -Y sign`
+///
+/// Returns `false` if the signature is made by another key or doesn't match the data.
+/// The namespace in the signature must be the expected one,
+/// otherwise [`ErrorKind::NamespaceMismatch`](../../error/enum.ErrorKind.html#variant.NamespaceMismatch) is returned,
+/// since accepting e.g. a `file` signature as a `git` signature is a security problem.
+/// The malformed signatures and the unknown hash algorithms are reported as errors.
+/// The RSA signatures using SHA-1 (`ssh-rsa`) are rejected with `ErrorKind::WeakSignature` like OpenSSH does.
+pub fn verify_sshsig(
+    key: &PublicKey,
+    data: &[u8],
+    namespace: &str,
+    armored: &str,
+) -> OsshResult<bool> {
+    verify_sshsig_impl(key, None, data, namespace, armored)
+}
+
+/// Verify the armored SSHSIG signature made by a key with the user certificate issued by the CA
+///
+/// This is how `ssh-keygen -Y verify` checks a `cert-authority` entry of the `allowed_signers` file:
+/// 1. The certificate must be signed by the CA, otherwise `false` is returned.
+/// 2. The certificate must be a user certificate, valid now, and list the principal,
+///    otherwise [`ErrorKind::CertificateRejected`](../../error/enum.ErrorKind.html#variant.CertificateRejected) is returned.
+/// 3. The signature is verified with the certified key like [`verify_sshsig()`](fn.verify_sshsig.html).
+///
+/// The signature may contain either the certificate, as signed by `ssh-keygen -Y sign` with the certificate,
+/// or the plain certified key.
+pub fn verify_sshsig_cert(
+    cert: &Certificate,
+    ca: &PublicKey,
+    principal: &str,
+    data: &[u8],
+    namespace: &str,
+    armored: &str,
+) -> OsshResult<bool> {
+    if cert.signature_key().blob()? != ca.blob()? || !cert.verify_signature()? {
+        return Ok(false);
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    if cert.cert_type() != CertType::User || !cert.is_valid_at(now) {
+        return Err(ErrorKind::CertificateRejected.into());
+    }
+    // Unlike the SSH servers, a certificate without any principal is not accepted for any principal
+    if !cert.valid_principals().iter().any(|p| p == principal) {
+        return Err(Error::with_failure(
+            ErrorKind::CertificateRejected,
+            principal,
+        ));
+    }
+    verify_sshsig_impl(
+        cert.public_key(),
+        Some(cert.blob()),
+        data,
+        namespace,
+        armored,
+    )
+}
+
+/// Verify the armored SSHSIG signature file of the data file, like `ssh-keygen -Y verify -s sig_path < data_path`
+///
+/// This is the same as [`verify_sshsig()`](fn.verify_sshsig.html) with the contents of the files.
+/// The files which can't be read are reported as [`ErrorKind::IOError`](../../error/enum.ErrorKind.html#variant.IOError).
+pub fn verify_sshsig_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    key: &PublicKey,
+    data_path: P,
+    sig_path: Q,
+    namespace: &str,
+) -> OsshResult<bool> {
+    let armored = fs::read_to_string(sig_path)?;
+    let data = fs::read(data_path)?;
+    verify_sshsig(key, &data, namespace, &armored)
+}
+
+/// Check the armored SSHSIG signature with the key embedded in it, like `ssh-keygen -Y check-novalidate`
+///
+/// The structure, the namespace and the signature are checked the same as [`verify_sshsig()`](fn.verify_sshsig.html),
+/// but **no trusted signer is involved**, so anyone can make a signature which passes the check.
+/// Returns the embedded key if the signature matches the data, or `None` otherwise.
+/// The caller must decide whether to trust the returned key, e.g. by its fingerprint.
+///
+/// If the signature is made with a certificate, the certified key is returned,
+/// without checking the certificate itself.
+pub fn check_sshsig_novalidate(
+    data: &[u8],
+    namespace: &str,
+    armored: &str,
+) -> OsshResult<Option<PublicKey>> {
+    let sig = Sshsig::parse(armored)?;
+    let key = if blob_keyname(&sig.pubkey)?.ends_with(CERT_SUFFIX) {
+        Certificate::from_blob(&sig.pubkey)?.public_key().clone()
+    } else {
+        PublicKey::from_blob(&sig.pubkey)?
+    };
+    if sig.verify_with(&key, data, namespace)? {
+        Ok(Some(key))
+    } else {
+        Ok(None)
+    }
+}
+
+// The fields of the SSHSIG blob
+struct Sshsig {
+    pubkey: Vec<u8>,
+    namespace: String,
+    hashalg: String,
+    signature: Vec<u8>,
+}
+
+impl Sshsig {
+    fn parse(armored: &str) -> OsshResult<Self> {
+        let blob = dearmor(armored)?;
+        let mut reader = Cursor::new(blob.as_slice());
+        let mut magic = [0u8; 6];
+        reader.read_exact(&mut magic)?;
+        if &magic != SSHSIG_MAGIC {
+            return Err(ErrorKind::InvalidKeyFormat.into());
+        }
+        let version = reader.read_uint32()?;
+        if version != SSHSIG_VERSION {
+            return Err(Error::with_failure(
+                ErrorKind::InvalidKeyFormat,
+                format!("SSHSIG version {}", version),
+            ));
+        }
+        let pubkey = reader.read_string()?;
+        let namespace = reader.read_utf8()?;
+        let _reserved = reader.read_string()?;
+        let hashalg = reader.read_utf8()?;
+        let signature = reader.read_string()?;
+        if reader.position() as usize != blob.len() {
+            return Err(ErrorKind::InvalidKeyFormat.into());
+        }
+        Ok(Sshsig {
+            pubkey,
+            namespace,
+            hashalg,
+            signature,
+        })
+    }
+
+    // Check the namespace and the hash algorithm, then verify the signature with the key
+    fn verify_with(&self, key: &PublicKey, data: &[u8], namespace: &str) -> OsshResult<bool> {
+        if self.namespace != namespace {
+            return Err(Error::with_failure(
+                ErrorKind::NamespaceMismatch,
+                self.namespace.as_str(),
+            ));
+        }
+        let hash = match self.hashalg.as_str() {
+            "sha256" => FingerprintHash::SHA256,
+            "sha512" => FingerprintHash::SHA512,
+            _ => {
+                return Err(Error::with_failure(
+                    ErrorKind::InvalidKeyFormat,
+                    self.hashalg.as_str(),
+                ))
+            }
+        };
+        if Cursor::new(self.signature.as_slice())
+            .read_utf8()
+            .ok()
+            .as_deref()
+            == Some(RSA_NAME)
+        {
+            return Err(ErrorKind::WeakSignature.into());
+        }
+
+        let signed = signed_data(namespace, &self.hashalg, &hash.hash(data))?;
+        Ok(key.verify_detailed(&signed, &self.signature)? == VerifyOutcome::Valid)
+    }
+}
+
+// Verify the signature made by the key, or the certificate of the key if given
+fn verify_sshsig_impl(
+    key: &PublicKey,
+    cert_blob: Option<&[u8]>,
+    data: &[u8],
+    namespace: &str,
+    armored: &str,
+) -> OsshResult<bool> {
+    let sig = Sshsig::parse(armored)?;
+    if sig.pubkey != key.blob()? && Some(sig.pubkey.as_slice()) != cert_blob {
+        return Ok(false);
+    }
+    sig.verify_with(key, data, namespace)
+}
+
+// The name of the hash algorithm in the SSHSIG blob
+fn hash_name(hash: FingerprintHash) -> OsshResult<&'static str> {
+    match hash {
+        FingerprintHash::SHA256 => Ok("sha256"),
+        FingerprintHash::SHA512 => Ok("sha512"),
+        FingerprintHash::MD5 => Err(Error::with_failure(ErrorKind::InvalidArgument, "md5")),
+        FingerprintHash::SHA1 => Err(Error::with_failure(ErrorKind::InvalidArgument, "sha1")),
+    }
+}
+
+// The data actually signed by the key, which binds the namespace to the hash of the message
+fn signed_data(namespace: &str, hashalg: &str, hash: &[u8]) -> OsshResult<Vec<u8>> {
+    let mut signed = Vec::new();
+    signed.extend_from_slice(SSHSIG_MAGIC);
+    signed.write_utf8(namespace)?;
+    signed.write_string(&[])?; // Reserved
+    signed.write_utf8(hashalg)?;
+    signed.write_string(hash)?;
+    Ok(signed)
+}
+
+fn dearmor(armored: &str) -> OsshResult<Vec<u8>> {
+    let mut lines = armored.lines().map(str::trim).filter(|l| !l.is_empty());
+    if lines.next() != Some(BEGIN_MARKER) {
+        return Err(ErrorKind::InvalidKeyFormat.into());
+    }
+    let mut b64 = String::new();
+    for line in lines.by_ref() {
+        if line == END_MARKER {
+            if lines.next().is_some() {
+                return Err(ErrorKind::InvalidKeyFormat.into());
+            }
+            return Ok(BASE64_STANDARD.decode(b64)?);
+        }
+        b64.push_str(line);
+    }
+    Err(ErrorKind::InvalidKeyFormat.into())
+}
+
+fn armor(blob: &[u8]) -> String {
+    let b64 = BASE64_STANDARD.encode(blob);
+    let mut armored = String::new();
+    armored.push_str(BEGIN_MARKER);
+    armored.push('\n');
+    // The base64 string is always ASCII
+    for line in b64.as_bytes().chunks(BASE64_LINE_LEN) {
+        armored.push_str(std::str::from_utf8(line).unwrap());
+        armored.push('\n');
+    }
+    armored.push_str(END_MARKER);
+    armored.push('\n');
+    armored
+}
@@ -0,0 +1,102 @@
+use super::ecdsa::{EcCurve, EcDsaPublicKey};
+use super::ed25519::Ed25519PublicKey;
+use super::rsa::{check_rsa_modulus, RsaPublicKey, RSA_MAX_SIZE};
+use super::PublicKey;
+use crate::error::*;
+use ed25519_dalek::PUBLIC_KEY_LENGTH;
+use openssl::bn::BigNum;
+
+/// Assemble a [`PublicKey`](struct.PublicKey.html) from the components of the key and a comment
+///
+/// This is useful when the key comes from somewhere else than a key file, e.g. an HSM.
+/// The components are only checked by [`build()`](#method.build), as they are by the key file parsers.
+///
+/// # Example
+/// ```rust
+/// use osshkeys::keys::{FingerprintHash, PublicKeyBuilder, PublicParts};
+///
+/// let key = PublicKeyBuilder::ed25519(&[
+///     0xca, 0x16, 0x06, 0x5b, 0x8f, 0x12, 0xb2, 0x84, 0xd8, 0xda, 0xde, 0x51, 0xc4, 0x7f, 0xb8, 0x9e,
+///     0xe8, 0x05, 0x73, 0x47, 0x6e, 0x26, 0x3d, 0x17, 0x7b, 0xe3, 0x6c, 0x44, 0x62, 0x87, 0x42, 0x01,
+/// ])
+/// .comment("alice@example.com")
+/// .build()
+/// .unwrap();
+/// assert_eq!(key.comment(), "alice@example.com");
+/// println!("{}", key.fingerprint_string(FingerprintHash::SHA256).unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct PublicKeyBuilder {
+    parts: Parts,
+    comment: String,
+}
+
+#[derive(Debug, Clone)]
+enum Parts {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    EcDsa { curve: EcCurve, point: Vec<u8> },
+    Ed25519(Vec<u8>),
+}
+
+impl PublicKeyBuilder {
+    /// Start with the RSA modulus `n` and the public exponent `e`, both big-endian unsigned integers
+    pub fn rsa(n: &[u8], e: &[u8]) -> Self {
+        Self::with_parts(Parts::Rsa {
+            n: n.to_vec(),
+            e: e.to_vec(),
+        })
+    }
+
+    /// Start with the EcDSA curve and the SEC1 encoded public point, either uncompressed or compressed
+    pub fn ecdsa(curve: EcCurve, point: &[u8]) -> Self {
+        Self::with_parts(Parts::EcDsa {
+            curve,
+            point: point.to_vec(),
+        })
+    }
+
+    /// Start with the 32 bytes Ed25519 public key
+    pub fn ed25519(key: &[u8]) -> Self {
+        Self::with_parts(Parts::Ed25519(key.to_vec()))
+    }
+
+    fn with_parts(parts: Parts) -> Self {
+        PublicKeyBuilder {
+            parts,
+            comment: String::new(),
+        }
+    }
+
+    /// Set the comment of the key
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = comment.into();
+        self
+    }
+
+    /// Check the components and build the public key
+    ///
+    /// - RSA: the modulus larger than 16384 bits is rejected with `ErrorKind::KeyTooLarge`.
+    /// - EcDSA: the point must be on the curve, otherwise `ErrorKind::InvalidKey` is returned.
+    /// - Ed25519: the key must be 32 bytes, otherwise `ErrorKind::InvalidLength` is returned.
+    pub fn build(self) -> OsshResult<PublicKey> {
+        let mut key: PublicKey = match self.parts {
+            Parts::Rsa { n, e } => {
+                let n = BigNum::from_slice(&n)?;
+                check_rsa_modulus(&n, RSA_MAX_SIZE)?;
+                RsaPublicKey::new(n, BigNum::from_slice(&e)?)?.into()
+            }
+            Parts::EcDsa { curve, point } => {
+                EcDsaPublicKey::from_point_bytes(curve, &point)?.into()
+            }
+            Parts::Ed25519(key) => {
+                let key: &[u8; PUBLIC_KEY_LENGTH] = key
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ErrorKind::InvalidLength)?;
+                Ed25519PublicKey::new(key)?.into()
+            }
+        };
+        *key.comment_mut() = self.comment;
+        Ok(key)
+    }
+}
@@ -0,0 +1,453 @@
+use super::dsa::DSA_NAME;
+use super::ecdsa::{ECDSA_SK_NAME, NIST_P256_NAME, NIST_P384_NAME, NIST_P521_NAME};
+use super::ed25519::{ED25519_NAME, ED25519_SK_NAME};
+use super::rsa::{RsaSignature, RSA_NAME};
+use super::{KeyPair, PublicKey, PublicParts, VerifyOutcome, VerifyPolicy};
+use crate::error::*;
+use crate::format::ossh_pubkey::{blob_keyname, decode_ossh_pubkey_blob};
+use crate::sshbuf::{SshReadExt, SshWriteExt};
+use base64::prelude::*;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// The certificate key types, with the plain key type and the number of its public key fields
+const CERT_KEY_TYPES: &[(&str, &str, usize)] = &[
+    ("ssh-rsa-cert-v01@openssh.com", RSA_NAME, 2),
+    ("ssh-dss-cert-v01@openssh.com", DSA_NAME, 4),
+    (
+        "ecdsa-sha2-nistp256-cert-v01@openssh.com",
+        NIST_P256_NAME,
+        2,
+    ),
+    (
+        "ecdsa-sha2-nistp384-cert-v01@openssh.com",
+        NIST_P384_NAME,
+        2,
+    ),
+    (
+        "ecdsa-sha2-nistp521-cert-v01@openssh.com",
+        NIST_P521_NAME,
+        2,
+    ),
+    (
+        "sk-ecdsa-sha2-nistp256-cert-v01@openssh.com",
+        ECDSA_SK_NAME,
+        3,
+    ),
+    ("ssh-ed25519-cert-v01@openssh.com", ED25519_NAME, 1),
+    ("sk-ssh-ed25519-cert-v01@openssh.com", ED25519_SK_NAME, 2),
+];
+
+const SSH2_CERT_TYPE_USER: u32 = 1;
+const SSH2_CERT_TYPE_HOST: u32 = 2;
+const NONCE_LEN: usize = 32;
+
+/// The type of the OpenSSH certificate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CertType {
+    /// The user certificate, to authenticate a user to the hosts
+    User,
+    /// The host certificate, to authenticate a host to the users
+    Host,
+}
+
+/// The contents of the certificate issued by [`KeyPair::sign_certificate()`](../struct.KeyPair.html#method.sign_certificate)
+///
+/// The default is a user certificate valid forever for any principal, without any options and extensions.
+/// Note that `ssh-keygen` adds the `permit-*` extensions to the user certificates by default,
+/// which are required to e.g. allocate a PTY.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertOptions {
+    /// The serial number
+    pub serial: u64,
+    /// Whether it is a user or a host certificate
+    pub cert_type: CertType,
+    /// The free-form identity of the key, logged by the servers
+    pub key_id: String,
+    /// The user names or the host names, or empty for any principal
+    pub valid_principals: Vec<String>,
+    /// The beginning of the validity window, in seconds since the UNIX epoch
+    pub valid_after: u64,
+    /// The end of the validity window, in seconds since the UNIX epoch
+    pub valid_before: u64,
+    /// The critical options and their values, e.g. `force-command`
+    pub critical_options: BTreeMap<String, String>,
+    /// The extensions, e.g. `permit-pty` with an empty value
+    pub extensions: BTreeMap<String, String>,
+}
+
+impl Default for CertOptions {
+    fn default() -> Self {
+        CertOptions {
+            serial: 0,
+            cert_type: CertType::User,
+            key_id: String::new(),
+            valid_principals: Vec::new(),
+            valid_after: 0,
+            valid_before: u64::MAX,
+            critical_options: BTreeMap::new(),
+            extensions: BTreeMap::new(),
+        }
+    }
+}
+
+/// The OpenSSH user or host certificate, e.g. `ssh-ed25519-cert-v01@openssh.com`
+///
+/// The certificate is the public key signed by a certificate authority (CA),
+/// together with the identity and the restrictions it grants,
+/// as described in the [`PROTOCOL.certkeys`](https://cvsweb.openbsd.org/src/usr.bin/ssh/PROTOCOL.certkeys) of OpenSSH.
+#[derive(Clone)]
+pub struct Certificate {
+    certname: &'static str,
+    nonce: Vec<u8>,
+    key: PublicKey,
+    serial: u64,
+    cert_type: CertType,
+    key_id: String,
+    valid_principals: Vec<String>,
+    valid_after: u64,
+    valid_before: u64,
+    critical_options: BTreeMap<String, String>,
+    extensions: BTreeMap<String, String>,
+    signature_key: PublicKey,
+    signature: Vec<u8>,
+    blob: Vec<u8>,
+    signed_len: usize,
+    comment: String,
+}
+
+impl Certificate {
+    /// Parse the certificate in the `.pub` file format, e.g. `ssh-ed25519-cert-v01@openssh.com AAAA... comment`
+    ///
+    /// The key type token must match the one in the blob.
+    pub fn from_keystring(keystr: &str) -> OsshResult<Self> {
+        let mut fields = keystr.split_whitespace();
+        let (certname, certb64) = match (fields.next(), fields.next()) {
+            (Some(certname), Some(certb64)) => (certname, certb64),
+            _ => return Err(ErrorKind::InvalidKeyFormat.into()),
+        };
+        let comment = fields.collect::<Vec<_>>().join(" ");
+        let mut cert = Self::from_blob(&BASE64_STANDARD.decode(certb64)?)?;
+        if cert.keyname() != certname {
+            return Err(ErrorKind::TypeNotMatch.into());
+        }
+        cert.comment = comment;
+        Ok(cert)
+    }
+
+    /// Parse the binary representation of the certificate
+    pub fn from_blob(blob: &[u8]) -> OsshResult<Self> {
+        let mut reader = Cursor::new(blob);
+        let certname = reader.read_utf8()?;
+        let &(certname, keyname, fields) = CERT_KEY_TYPES
+            .iter()
+            .find(|(name, _, _)| *name == certname)
+            .ok_or_else(|| Error::with_failure(ErrorKind::UnsupportType, certname.as_str()))?;
+        let nonce = reader.read_string()?;
+
+        // Rebuild the blob of the plain public key from its fields
+        let mut keyblob = Vec::new();
+        keyblob.write_utf8(keyname)?;
+        for _ in 0..fields {
+            keyblob.write_string(&reader.read_string()?)?;
+        }
+        let key = decode_ossh_pubkey_blob(&keyblob)?;
+
+        let serial = reader.read_uint64()?;
+        let cert_type = match reader.read_uint32()? {
+            SSH2_CERT_TYPE_USER => CertType::User,
+            SSH2_CERT_TYPE_HOST => CertType::Host,
+            _ => return Err(ErrorKind::InvalidKeyFormat.into()),
+        };
+        let key_id = reader.read_utf8()?;
+        let valid_principals = read_principals(&reader.read_string()?)?;
+        let valid_after = reader.read_uint64()?;
+        let valid_before = reader.read_uint64()?;
+        let critical_options = read_options(&reader.read_string()?)?;
+        let extensions = read_options(&reader.read_string()?)?;
+        let _reserved = reader.read_string()?;
+        let signature_key = decode_ossh_pubkey_blob(&reader.read_string()?)?;
+        // The signature covers everything before it
+        let signed_len = reader.position() as usize;
+        let signature = reader.read_string()?;
+        if reader.position() as usize != blob.len() {
+            return Err(ErrorKind::InvalidKeyFormat.into());
+        }
+
+        Ok(Certificate {
+            certname,
+            nonce,
+            key,
+            serial,
+            cert_type,
+            key_id,
+            valid_principals,
+            valid_after,
+            valid_before,
+            critical_options,
+            extensions,
+            signature_key,
+            signature,
+            blob: blob.to_vec(),
+            signed_len,
+            comment: String::new(),
+        })
+    }
+
+    /// Verify the signature of the CA over the certificate
+    ///
+    /// Only the signature is checked, the caller should decide whether to trust the
+    /// [`signature_key()`](#method.signature_key) and check the validity window and the principals.
+    ///
+    /// Same as the default `CASignatureAlgorithms` of OpenSSH, the `ssh-rsa` (SHA-1) and `ssh-dss` signatures
+    /// are rejected with [`ErrorKind::WeakSignature`](../../error/enum.ErrorKind.html#variant.WeakSignature),
+    /// use [`verify_signature_with_policy()`](#method.verify_signature_with_policy) to accept them.
+    pub fn verify_signature(&self) -> OsshResult<bool> {
+        self.verify_signature_with_policy(VerifyPolicy::strict())
+    }
+
+    /// Verify the signature of the CA only if its algorithm is allowed by the policy
+    ///
+    /// The algorithm is read from the signature, e.g. `rsa-sha2-512`, rather than decided by the CA key.
+    pub fn verify_signature_with_policy(&self, policy: VerifyPolicy) -> OsshResult<bool> {
+        let alg = Cursor::new(&self.signature).read_utf8().unwrap_or_default();
+        let allowed = if alg == DSA_NAME {
+            policy.allow_dsa
+        } else {
+            policy.allow_rsa_sha1 || RsaSignature::from_name(&alg) != Some(RsaSignature::SHA1)
+        };
+        if !allowed {
+            return Err(ErrorKind::WeakSignature.into());
+        }
+        let outcome = self
+            .signature_key
+            .verify_detailed(&self.blob[..self.signed_len], &self.signature)?;
+        Ok(outcome == VerifyOutcome::Valid)
+    }
+
+    /// The certificate key type name, e.g. `ssh-ed25519-cert-v01@openssh.com`
+    pub fn keyname(&self) -> &'static str {
+        self.certname
+    }
+
+    /// The random nonce chosen by the CA
+    pub fn nonce(&self) -> &[u8] {
+        &self.nonce
+    }
+
+    /// The public key being certified
+    pub fn public_key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// The serial number set by the CA
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    /// Whether it is a user or a host certificate
+    pub fn cert_type(&self) -> CertType {
+        self.cert_type
+    }
+
+    /// Check if it is a host certificate, which `sshd` presents to the clients
+    pub fn is_host_cert(&self) -> bool {
+        self.cert_type == CertType::Host
+    }
+
+    /// The free-form identity of the key, logged by the servers
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// The user names or the host names the certificate is valid for
+    ///
+    /// An empty list means the certificate is valid for any principal.
+    pub fn valid_principals(&self) -> &[String] {
+        &self.valid_principals
+    }
+
+    /// The beginning of the validity window, in seconds since the UNIX epoch
+    pub fn valid_after(&self) -> u64 {
+        self.valid_after
+    }
+
+    /// The end of the validity window, in seconds since the UNIX epoch
+    ///
+    /// `u64::MAX` means the certificate never expires.
+    pub fn valid_before(&self) -> u64 {
+        self.valid_before
+    }
+
+    /// Check if the time is in the validity window, in seconds since the UNIX epoch
+    ///
+    /// As OpenSSH, `valid_after` is included and `valid_before` is excluded.
+    pub fn is_valid_at(&self, timestamp: u64) -> bool {
+        self.valid_after <= timestamp && timestamp < self.valid_before
+    }
+
+    /// Check if the time is in the validity window, widened by the clock skew on both sides
+    ///
+    /// The certificate is valid from `valid_after - skew` (included) to `valid_before + skew` (excluded),
+    /// and a `valid_before` of `u64::MAX` still means it never expires.
+    /// The time and the skew are truncated to whole seconds, and the time before the UNIX epoch is taken as `0`.
+    pub fn is_valid_at_with_skew(&self, now: SystemTime, skew: Duration) -> bool {
+        let now = now
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let skew = skew.as_secs();
+        self.valid_after.saturating_sub(skew) <= now
+            && (self.valid_before == u64::MAX || now < self.valid_before.saturating_add(skew))
+    }
+
+    /// The critical options, e.g. `force-command` and `source-address`, with their values
+    pub fn critical_options(&self) -> &BTreeMap<String, String> {
+        &self.critical_options
+    }
+
+    /// The extensions, e.g. `permit-pty`, whose values are usually empty
+    pub fn extensions(&self) -> &BTreeMap<String, String> {
+        &self.extensions
+    }
+
+    /// The public key of the CA which signed the certificate
+    pub fn signature_key(&self) -> &PublicKey {
+        &self.signature_key
+    }
+
+    /// The signature blob of the CA, containing the signature algorithm and the signature
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// The binary representation of the certificate
+    pub fn blob(&self) -> &[u8] {
+        &self.blob
+    }
+
+    /// The comment after the blob in the `.pub` file
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    /// Get the mutable reference of the comment
+    pub fn comment_mut(&mut self) -> &mut String {
+        &mut self.comment
+    }
+
+    /// Serialize the certificate in the `.pub` file format, e.g. `ssh-ed25519-cert-v01@openssh.com AAAA... comment`
+    pub fn serialize_certificate(&self) -> String {
+        let mut keystr = format!("{} {}", self.certname, BASE64_STANDARD.encode(&self.blob));
+        if !self.comment.is_empty() {
+            keystr.push(' ');
+            keystr.push_str(&self.comment);
+        }
+        keystr
+    }
+}
+
+// Sign the certificate of the subject key with the CA key, see `KeyPair::sign_certificate()`
+pub(crate) fn sign_certificate(
+    ca: &KeyPair,
+    subject: &PublicKey,
+    opts: CertOptions,
+) -> OsshResult<Certificate> {
+    if opts.valid_after > opts.valid_before {
+        return Err(Error::with_failure(
+            ErrorKind::InvalidArgument,
+            "The validity window is empty",
+        ));
+    }
+    let keyblob = subject.blob()?;
+    let keyname = blob_keyname(&keyblob)?;
+    let certname = CERT_KEY_TYPES
+        .iter()
+        .find(|(_, name, _)| *name == keyname)
+        .map(|(certname, _, _)| *certname)
+        .ok_or_else(|| Error::with_failure(ErrorKind::UnsupportType, keyname))?;
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut blob = Vec::new();
+    blob.write_utf8(certname)?;
+    blob.write_string(&nonce)?;
+    // The public key fields follow the key type name
+    blob.extend_from_slice(&keyblob[4 + keyname.len()..]);
+    blob.write_uint64(opts.serial)?;
+    blob.write_uint32(match opts.cert_type {
+        CertType::User => SSH2_CERT_TYPE_USER,
+        CertType::Host => SSH2_CERT_TYPE_HOST,
+    })?;
+    blob.write_utf8(&opts.key_id)?;
+    let mut principals = Vec::new();
+    for principal in &opts.valid_principals {
+        principals.write_utf8(principal)?;
+    }
+    blob.write_string(&principals)?;
+    blob.write_uint64(opts.valid_after)?;
+    blob.write_uint64(opts.valid_before)?;
+    blob.write_string(&write_options(&opts.critical_options)?)?;
+    blob.write_string(&write_options(&opts.extensions)?)?;
+    blob.write_string(&[])?; // Reserved
+    blob.write_string(&ca.blob()?)?;
+    // OpenSSH signs the certificates with `rsa-sha2-512` by default
+    let signature = ca.sign_with_alg(&blob, Some(RsaSignature::SHA2_512))?;
+    blob.write_string(&signature)?;
+
+    let mut cert = Certificate::from_blob(&blob)?;
+    cert.comment = subject.comment().to_owned();
+    Ok(cert)
+}
+
+fn read_principals(data: &[u8]) -> OsshResult<Vec<String>> {
+    let mut reader = Cursor::new(data);
+    let mut principals = Vec::new();
+    while (reader.position() as usize) < data.len() {
+        principals.push(reader.read_utf8()?);
+    }
+    Ok(principals)
+}
+
+// The options are the name and the data pairs, where the non-empty data contains a string
+fn read_options(data: &[u8]) -> OsshResult<BTreeMap<String, String>> {
+    let mut reader = Cursor::new(data);
+    let mut options = BTreeMap::new();
+    while (reader.position() as usize) < data.len() {
+        let name = reader.read_utf8()?;
+        let data = reader.read_string()?;
+        let value = if data.is_empty() {
+            String::new()
+        } else {
+            let mut inner = Cursor::new(data.as_slice());
+            let value = inner.read_utf8()?;
+            if inner.position() as usize != data.len() {
+                return Err(ErrorKind::InvalidKeyFormat.into());
+            }
+            value
+        };
+        if options.insert(name, value).is_some() {
+            return Err(ErrorKind::InvalidKeyFormat.into());
+        }
+    }
+    Ok(options)
+}
+
+// The options are sorted by the name, as required by OpenSSH
+fn write_options(options: &BTreeMap<String, String>) -> OsshResult<Vec<u8>> {
+    let mut buf = Vec::new();
+    for (name, value) in options {
+        buf.write_utf8(name)?;
+        if value.is_empty() {
+            buf.write_string(&[])?;
+        } else {
+            let mut data = Vec::new();
+            data.write_utf8(value)?;
+            buf.write_string(&data)?;
+        }
+    }
+    Ok(buf)
+}
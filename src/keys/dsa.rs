@@ -1,8 +1,9 @@
 use super::{Key, PrivateParts, PublicParts};
 use crate::error::{Error, ErrorKind, OsshResult};
+use crate::format::der::is_canonical_dsa_sig;
 use crate::format::ossh_pubkey::*;
 use openssl::bn::BigNum;
-use openssl::dsa::{Dsa, DsaRef};
+use openssl::dsa::{Dsa, DsaRef, DsaSig};
 use openssl::hash::MessageDigest;
 use openssl::pkey::{PKey, Private, Public};
 use openssl::sign::{Signer, Verifier};
@@ -65,6 +66,9 @@ impl PublicParts for DsaPublicKey {
     }
 
     fn verify(&self, data: &[u8], sig: &[u8]) -> Result<bool, Error> {
+        if !is_canonical_dsa_sig(sig) {
+            return Ok(false);
+        }
         let pkey = PKey::from_dsa(self.dsa.clone())?;
         let mut veri = Verifier::new(MessageDigest::sha1(), &pkey)?;
         veri.update(data)?;
@@ -72,6 +76,20 @@ impl PublicParts for DsaPublicKey {
     }
 }
 
+impl DsaPublicKey {
+    // Verify the `ssh-dss` signature, which is the 160 bits `r` and `s` concatenated
+    // Return `None` if the signature is malformed
+    pub(crate) fn verify_ssh(&self, data: &[u8], sig: &[u8]) -> OsshResult<Option<bool>> {
+        if sig.len() != 40 {
+            return Ok(None);
+        }
+        let r = BigNum::from_slice(&sig[..20])?;
+        let s = BigNum::from_slice(&sig[20..])?;
+        let der = DsaSig::from_private_components(r, s)?.to_der()?;
+        Ok(Some(self.verify(data, &der)?))
+    }
+}
+
 impl PartialEq for DsaPublicKey {
     fn eq(&self, other: &Self) -> bool {
         (self.dsa.p() == other.dsa.p())
@@ -88,6 +106,10 @@ impl fmt::Display for DsaPublicKey {
 }
 
 /// Represent the DSA key pair
+///
+/// The keys of any size can be loaded from the files, e.g. the 2048 bits PEM keys,
+/// but only the 1024 bits keys with 160 bits `q` can make the `ssh-dss` signatures.
+#[derive(Clone)]
 pub struct DsaKeyPair {
     dsa: Dsa<Private>,
 }
@@ -150,6 +172,21 @@ impl PublicParts for DsaKeyPair {
     }
 }
 
+impl DsaKeyPair {
+    // Sign as `ssh-dss`, returning the 160 bits `r` and `s` concatenated
+    //
+    // The larger keys usually have a longer `q`, which makes the signature not fit in the format.
+    pub(crate) fn sign_ssh(&self, data: &[u8]) -> OsshResult<Vec<u8>> {
+        if self.dsa.q().num_bits() > 160 {
+            return Err(ErrorKind::InvalidKeySize.into());
+        }
+        let sig = DsaSig::from_der(&self.sign(data)?)?;
+        let mut buf = sig.r().to_vec_padded(20)?;
+        buf.extend(sig.s().to_vec_padded(20)?);
+        Ok(buf)
+    }
+}
+
 impl PrivateParts for DsaKeyPair {
     fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
         let pkey = PKey::from_dsa(self.dsa.clone())?;
@@ -223,4 +260,18 @@ mod test {
         let key = get_test_pubkey().unwrap();
         assert_eq!(key.size(), 1024);
     }
+
+    #[test]
+    fn dsa_sign_ssh() {
+        let key = DsaKeyPair::generate(0).unwrap();
+        let sig = key.sign_ssh(b"data").unwrap();
+        assert_eq!(sig.len(), 40);
+        let r = BigNum::from_slice(&sig[..20]).unwrap();
+        let s = BigNum::from_slice(&sig[20..]).unwrap();
+        let der = DsaSig::from_private_components(r, s)
+            .unwrap()
+            .to_der()
+            .unwrap();
+        assert!(key.verify(b"data", &der).unwrap());
+    }
 }
@@ -1,24 +1,39 @@
-use super::{Key, PrivateParts, PublicParts};
+use super::{sk_signed_data, Key, PrivateParts, PublicParts};
 use crate::error::{Error, ErrorKind, OsshResult};
+use crate::format::der::is_canonical_dsa_sig;
 use crate::format::ossh_pubkey::*;
-use openssl::bn::{BigNumContext, BigNumRef};
-use openssl::ec::{EcGroup, EcKey, EcKeyRef, EcPoint, EcPointRef};
+use crate::sshbuf::{SshReadExt, SshWriteExt};
+use openssl::bn::{BigNum, BigNumContext, BigNumRef};
+use openssl::ec::{EcGroup, EcGroupRef, EcKey, EcKeyRef, EcPoint, EcPointRef, PointConversionForm};
+use openssl::ecdsa::EcdsaSig;
 use openssl::hash::MessageDigest;
 use openssl::nid::Nid;
 use openssl::pkey::{PKey, Private, Public};
 use openssl::sign::{Signer, Verifier};
 use std::fmt;
+use std::io;
 use std::str::FromStr;
+use zeroize::Zeroizing;
 
-const ECDSA_DEF_SIZE: usize = 256;
+pub(crate) const ECDSA_DEF_SIZE: usize = 256;
 /// The name of 256 bits curve key returned by [`Key::keyname()`](../trait.Key.html#method.keyname)
 pub const NIST_P256_NAME: &str = "ecdsa-sha2-nistp256";
 /// The name of 384 bits curve key returned by [`Key::keyname()`](../trait.Key.html#method.keyname)
 pub const NIST_P384_NAME: &str = "ecdsa-sha2-nistp384";
 /// The name of 521 bits curve key returned by [`Key::keyname()`](../trait.Key.html#method.keyname)
 pub const NIST_P521_NAME: &str = "ecdsa-sha2-nistp521";
+/// The name of secp256k1 curve key returned by [`Key::keyname()`](../trait.Key.html#method.keyname)
+///
+/// OpenSSH doesn't define a key type for the secp256k1 curve, so this is a **non-standard** name
+/// only understood by this crate. Keys with it in the OpenSSH formats can't be used with OpenSSH,
+/// use PEM, DER or PKCS#8 to exchange the secp256k1 keys with other tools.
+pub const SECP256K1_NAME: &str = "ecdsa-sha2-secp256k1@osshkeys";
 /// The short name of ECDSA returned by [`Key::short_keyname()`](../trait.Key.html#method.short_keyname)
 pub const ECDSA_SHORT_NAME: &str = "ECDSA";
+/// The key name of the FIDO/U2F security key returned by [`Key::keyname()`](../trait.Key.html#method.keyname)
+pub const ECDSA_SK_NAME: &str = "sk-ecdsa-sha2-nistp256@openssh.com";
+/// The short key name of the FIDO/U2F security key returned by [`Key::short_keyname()`](../trait.Key.html#method.short_keyname)
+pub const ECDSA_SK_SHORT_NAME: &str = "ECDSA-SK";
 
 /// An enum of the supported elliptic curves
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -26,6 +41,8 @@ pub enum EcCurve {
     Nistp256,
     Nistp384,
     Nistp521,
+    /// The secp256k1 curve, which has no standard OpenSSH key type (see [`SECP256K1_NAME`])
+    Secp256k1,
 }
 
 impl EcCurve {
@@ -35,6 +52,7 @@ impl EcCurve {
             NIST_P256_NAME => Ok(EcCurve::Nistp256),
             NIST_P384_NAME => Ok(EcCurve::Nistp384),
             NIST_P521_NAME => Ok(EcCurve::Nistp521),
+            SECP256K1_NAME => Ok(EcCurve::Secp256k1),
             _ => Err(ErrorKind::UnsupportCurve.into()),
         }
     }
@@ -45,6 +63,7 @@ impl EcCurve {
             EcCurve::Nistp256 => 256,
             EcCurve::Nistp384 => 384,
             EcCurve::Nistp521 => 521,
+            EcCurve::Secp256k1 => 256,
         }
     }
 
@@ -54,6 +73,7 @@ impl EcCurve {
             EcCurve::Nistp256 => NIST_P256_NAME,
             EcCurve::Nistp384 => NIST_P384_NAME,
             EcCurve::Nistp521 => NIST_P521_NAME,
+            EcCurve::Secp256k1 => SECP256K1_NAME,
         }
     }
 
@@ -63,18 +83,102 @@ impl EcCurve {
             EcCurve::Nistp256 => "nistp256",
             EcCurve::Nistp384 => "nistp384",
             EcCurve::Nistp521 => "nistp521",
+            EcCurve::Secp256k1 => "secp256k1",
         }
     }
 
+    // The hash function used by the `ecdsa-sha2-*` signature
+    fn digest(self) -> MessageDigest {
+        match self {
+            EcCurve::Nistp256 | EcCurve::Secp256k1 => MessageDigest::sha256(),
+            EcCurve::Nistp384 => MessageDigest::sha384(),
+            EcCurve::Nistp521 => MessageDigest::sha512(),
+        }
+    }
+
+    /// Find the curve of the group
+    ///
+    /// The group using the explicit parameters instead of the named curve OID
+    /// is matched by comparing its parameters with the supported curves.
+    pub(crate) fn from_group(group: &EcGroupRef) -> OsshResult<Self> {
+        if let Some(nid) = group.curve_name() {
+            return match nid {
+                Nid::X9_62_PRIME256V1 => Ok(EcCurve::Nistp256),
+                Nid::SECP384R1 => Ok(EcCurve::Nistp384),
+                Nid::SECP521R1 => Ok(EcCurve::Nistp521),
+                Nid::SECP256K1 => Ok(EcCurve::Secp256k1),
+                _ => Err(ErrorKind::UnsupportCurve.into()),
+            };
+        }
+
+        let params =
+            group_params(group).map_err(|e| Error::with_error(ErrorKind::UnsupportCurve, e))?;
+        for curve in [
+            EcCurve::Nistp256,
+            EcCurve::Nistp384,
+            EcCurve::Nistp521,
+            EcCurve::Secp256k1,
+        ] {
+            // The curve may be unavailable in the linked OpenSSL, e.g. secp256k1
+            let named: EcGroup = match curve.try_into() {
+                Ok(group) => group,
+                Err(_) => continue,
+            };
+            if group_params(&named)? == params {
+                return Ok(curve);
+            }
+        }
+        Err(ErrorKind::UnsupportCurve.into())
+    }
+
     fn nid(self) -> Nid {
         match self {
             EcCurve::Nistp256 => Nid::X9_62_PRIME256V1,
             EcCurve::Nistp384 => Nid::SECP384R1,
             EcCurve::Nistp521 => Nid::SECP521R1,
+            EcCurve::Secp256k1 => Nid::SECP256K1,
         }
     }
 }
 
+// The parameters which define the prime field curve: p, a, b, order, cofactor and generator
+fn group_params(group: &EcGroupRef) -> Result<Vec<Vec<u8>>, openssl::error::ErrorStack> {
+    let mut ctx = BigNumContext::new()?;
+    let mut p = BigNum::new()?;
+    let mut a = BigNum::new()?;
+    let mut b = BigNum::new()?;
+    let mut order = BigNum::new()?;
+    let mut cofactor = BigNum::new()?;
+    group.components_gfp(&mut p, &mut a, &mut b, &mut ctx)?;
+    group.order(&mut order, &mut ctx)?;
+    group.cofactor(&mut cofactor, &mut ctx)?;
+    // The groups parsed from the key files always have the generator
+    #[allow(deprecated)]
+    let generator =
+        group
+            .generator()
+            .to_bytes(group, PointConversionForm::UNCOMPRESSED, &mut ctx)?;
+    Ok(vec![
+        p.to_vec(),
+        a.to_vec(),
+        b.to_vec(),
+        order.to_vec(),
+        cofactor.to_vec(),
+        generator,
+    ])
+}
+
+// Move the public point from the group with explicit parameters to the named group
+fn named_public_point(
+    group: &EcGroupRef,
+    point: &EcPointRef,
+    named: &EcGroupRef,
+) -> Result<EcPoint, openssl::error::ErrorStack> {
+    let mut ctx = BigNumContext::new()?;
+    let bytes = point.to_bytes(group, PointConversionForm::UNCOMPRESSED, &mut ctx)?;
+    EcPoint::from_bytes(named, &bytes, &mut ctx)
+}
+
 impl FromStr for EcCurve {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -82,6 +186,7 @@ impl FromStr for EcCurve {
             "nistp256" => Ok(EcCurve::Nistp256),
             "nistp384" => Ok(EcCurve::Nistp384),
             "nistp521" => Ok(EcCurve::Nistp521),
+            "secp256k1" => Ok(EcCurve::Secp256k1),
             _ => Err(ErrorKind::UnsupportCurve.into()),
         }
     }
@@ -116,21 +221,49 @@ impl EcDsaPublicKey {
     }
 
     pub(crate) fn from_ossl_ec(key: EcKey<Public>) -> OsshResult<Self> {
-        let curve = match key.group().curve_name().unwrap_or(Nid::UNDEF) {
-            Nid::X9_62_PRIME256V1 => EcCurve::Nistp256,
-            Nid::SECP384R1 => EcCurve::Nistp384,
-            Nid::SECP521R1 => EcCurve::Nistp521,
-            _ => return Err(ErrorKind::UnsupportCurve.into()),
-        };
+        let curve = EcCurve::from_group(key.group())?;
+        if key.group().curve_name().is_none() {
+            // Explicit parameters, use the named curve instead
+            let group: EcGroup = curve.try_into()?;
+            let point = named_public_point(key.group(), key.public_key(), &group)?;
+            return Ok(Self::new(curve, &point)?);
+        }
 
         Ok(Self { key, curve })
     }
 
-    pub(crate) fn from_bytes(curve: EcCurve, public_key: &[u8]) -> OsshResult<Self> {
-        Ok(Self::new(
-            curve,
-            into_ec_point(curve, public_key)?.as_ref(),
-        )?)
+    /// Create the EcDSA public key from the SEC1 encoded public point, either uncompressed or compressed
+    ///
+    /// The uncompressed point (`04 || x || y`) is the encoding used by OpenSSH,
+    /// while the compressed one (`02 || x` or `03 || x`) is used by e.g. some TLS and blockchain tools.
+    /// A point which can't be decoded, doesn't lie on the curve or is the point at infinity
+    /// is rejected with `ErrorKind::InvalidKey`.
+    pub fn from_point_bytes(curve: EcCurve, point: &[u8]) -> OsshResult<Self> {
+        if !matches!(point.first(), Some(0x02..=0x04)) {
+            return Err(ErrorKind::InvalidKey.into());
+        }
+        let group: EcGroup = curve.try_into()?;
+        let mut ctx = BigNumContext::new()?;
+        let point =
+            EcPoint::from_bytes(&group, point, &mut ctx).map_err(|_| ErrorKind::InvalidKey)?;
+        if point.is_infinity(&group) || !point.is_on_curve(&group, &mut ctx)? {
+            return Err(ErrorKind::InvalidKey.into());
+        }
+        Ok(Self::new(curve, &point)?)
+    }
+
+    /// Encode the public point as SEC1, either uncompressed like OpenSSH or compressed
+    pub fn to_point_bytes(&self, compressed: bool) -> OsshResult<Vec<u8>> {
+        let form = if compressed {
+            PointConversionForm::COMPRESSED
+        } else {
+            PointConversionForm::UNCOMPRESSED
+        };
+        let mut ctx = BigNumContext::new()?;
+        Ok(self
+            .key
+            .public_key()
+            .to_bytes(self.key.group(), form, &mut ctx)?)
     }
 
     /// Get the key's elliptic curve type
@@ -138,7 +271,6 @@ impl EcDsaPublicKey {
         self.curve
     }
 
-    #[allow(unused)]
     pub(crate) fn ossl_ec(&self) -> &EcKeyRef<Public> {
         &self.key
     }
@@ -168,6 +300,9 @@ impl PublicParts for EcDsaPublicKey {
     }
 
     fn verify(&self, data: &[u8], sig: &[u8]) -> Result<bool, Error> {
+        if !is_canonical_dsa_sig(sig) {
+            return Ok(false);
+        }
         let pkey = PKey::from_ec_key(self.key.clone())?;
         let mut veri = Verifier::new(MessageDigest::sha1(), &pkey)?;
         veri.update(data)?;
@@ -175,6 +310,23 @@ impl PublicParts for EcDsaPublicKey {
     }
 }
 
+impl EcDsaPublicKey {
+    // Verify the `ecdsa-sha2-*` signature, which is encoded as `mpint r, mpint s`
+    // Return `None` if the signature is malformed
+    pub(crate) fn verify_ssh(&self, data: &[u8], sig: &[u8]) -> OsshResult<Option<bool>> {
+        let mut reader = io::Cursor::new(sig);
+        let (r, s) = match (reader.read_mpint(), reader.read_mpint()) {
+            (Ok(r), Ok(s)) if reader.position() as usize == sig.len() => (r, s),
+            _ => return Ok(None),
+        };
+        let der = EcdsaSig::from_private_components(r, s)?.to_der()?;
+        let pkey = PKey::from_ec_key(self.key.clone())?;
+        let mut veri = Verifier::new(self.curve.digest(), &pkey)?;
+        veri.update(data)?;
+        Ok(Some(veri.verify(&der)?))
+    }
+}
+
 impl PartialEq for EcDsaPublicKey {
     fn eq(&self, other: &Self) -> bool {
         let mut bn_ctx = BigNumContext::new().unwrap();
@@ -195,6 +347,7 @@ impl fmt::Display for EcDsaPublicKey {
 }
 
 /// Represent the EcDSA key pair
+#[derive(Clone)]
 pub struct EcDsaKeyPair {
     key: EcKey<Private>,
     curve: EcCurve,
@@ -202,12 +355,13 @@ pub struct EcDsaKeyPair {
 
 impl EcDsaKeyPair {
     pub(crate) fn from_ossl_ec(key: EcKey<Private>) -> OsshResult<Self> {
-        let curve = match key.group().curve_name().unwrap_or(Nid::UNDEF) {
-            Nid::X9_62_PRIME256V1 => EcCurve::Nistp256,
-            Nid::SECP384R1 => EcCurve::Nistp384,
-            Nid::SECP521R1 => EcCurve::Nistp521,
-            _ => return Err(ErrorKind::UnsupportCurve.into()),
-        };
+        let curve = EcCurve::from_group(key.group())?;
+        if key.group().curve_name().is_none() {
+            // Explicit parameters, use the named curve instead
+            let group: EcGroup = curve.try_into()?;
+            let point = named_public_point(key.group(), key.public_key(), &group)?;
+            return Self::new(curve, &point, key.private_key());
+        }
 
         Ok(Self { key, curve })
     }
@@ -243,8 +397,9 @@ impl EcDsaKeyPair {
 
     /// Generate EcDSA key pair
     ///
-    /// The bits parameter should be 256, 284, 521 bits or `0` to use default length (256 bits).
-    /// Different key length is corresponding to different curve.
+    /// The bits parameter should be 256, 384, 521 bits or `0` to use default length (256 bits).
+    /// Different key length is corresponding to different curve,
+    /// use [`generate_with_curve()`](struct.EcDsaKeyPair.html#method.generate_with_curve) to specify the curve directly.
     pub fn generate(mut bits: usize) -> OsshResult<Self> {
         if bits == 0 {
             bits = ECDSA_DEF_SIZE;
@@ -255,7 +410,18 @@ impl EcDsaKeyPair {
             521 => EcCurve::Nistp521,
             _ => return Err(Error::from_kind(ErrorKind::InvalidKeySize)),
         };
-        let group: EcGroup = curve.try_into()?;
+        Self::generate_with_curve(curve)
+    }
+
+    /// Generate EcDSA key pair on the elliptic curve
+    ///
+    /// [`EcCurve::Secp256k1`](enum.EcCurve.html#variant.Secp256k1) is only reachable from here,
+    /// as `generate(256)` always picks the NIST P-256 curve.
+    /// It fails with `ErrorKind::UnsupportCurve` if the linked OpenSSL doesn't support the curve.
+    pub fn generate_with_curve(curve: EcCurve) -> OsshResult<Self> {
+        let group: EcGroup = curve
+            .try_into()
+            .map_err(|e| Error::with_error(ErrorKind::UnsupportCurve, e))?;
 
         Ok(EcDsaKeyPair {
             key: EcKey::generate(&group)?,
@@ -272,6 +438,22 @@ impl EcDsaKeyPair {
     pub fn clone_public_key(&self) -> Result<EcDsaPublicKey, Error> {
         Ok(EcDsaPublicKey::new(self.curve, self.key.public_key())?)
     }
+
+    /// Export the raw private scalar as big-endian bytes
+    ///
+    /// The bytes are left padded to the length of the curve order, i.e. 32, 48 or 66 bytes,
+    /// and cleared from the memory when the returned value is dropped.
+    ///
+    /// This is an advanced API for e.g. the HSM import or the threshold signing.
+    /// The scalar is the unencrypted private key itself, and an external signer using it
+    /// with a repeated or biased nonce also leaks it through the signatures.
+    pub fn private_scalar_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let scalar = Zeroizing::new(self.key.private_key().to_vec());
+        let len = (self.curve.size() + 7) / 8;
+        let mut padded = Zeroizing::new(vec![0u8; len]);
+        padded[len - scalar.len()..].copy_from_slice(&scalar);
+        padded
+    }
 }
 
 impl Key for EcDsaKeyPair {
@@ -298,6 +480,21 @@ impl PublicParts for EcDsaKeyPair {
     }
 }
 
+impl EcDsaKeyPair {
+    // Sign as `ecdsa-sha2-*`, returning the `mpint r, mpint s` encoded signature
+    pub(crate) fn sign_ssh(&self, data: &[u8]) -> OsshResult<Vec<u8>> {
+        let pkey = PKey::from_ec_key(self.key.clone())?;
+        let mut sign = Signer::new(self.curve.digest(), &pkey)?;
+        sign.update(data)?;
+        let sig = EcdsaSig::from_der(&sign.sign_to_vec()?)?;
+
+        let mut buf = Vec::new();
+        buf.write_mpint(sig.r())?;
+        buf.write_mpint(sig.s())?;
+        Ok(buf)
+    }
+}
+
 impl PrivateParts for EcDsaKeyPair {
     fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
         let pkey = PKey::from_ec_key(self.key.clone())?;
@@ -307,12 +504,250 @@ impl PrivateParts for EcDsaKeyPair {
     }
 }
 
+/// Represent the EcDSA public key of a FIDO/U2F security key (`sk-ecdsa-sha2-nistp256@openssh.com`)
+///
+/// The key is always on the NIST P-256 curve,
+/// and stores the application (relying party ID, usually `ssh:`) besides the public point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EcDsaSkPublicKey {
+    key: EcDsaPublicKey,
+    application: String,
+}
+
+impl EcDsaSkPublicKey {
+    /// Create the public key from the SEC1 encoded public point on the NIST P-256 curve and the application
+    pub fn new(public_key: &[u8], application: &str) -> OsshResult<Self> {
+        Ok(Self {
+            key: EcDsaPublicKey::from_point_bytes(EcCurve::Nistp256, public_key)?,
+            application: application.to_owned(),
+        })
+    }
+
+    /// The application (relying party ID) of the key, e.g. `ssh:`
+    pub fn application(&self) -> &str {
+        &self.application
+    }
+
+    /// The EcDSA public key of the token
+    pub fn ecdsa_key(&self) -> &EcDsaPublicKey {
+        &self.key
+    }
+
+    /// Verify the signature made by the security key
+    ///
+    /// The signature is the SSH encoded `mpint r, mpint s`, the same as the `ecdsa-sha2-nistp256` signature.
+    /// The token signs `SHA256(application) || flags || counter || SHA256(data)`,
+    /// so the flags and the signature counter returned with the signature are also required.
+    /// Checking the flags (e.g. [`SK_USER_PRESENCE_REQD`](../constant.SK_USER_PRESENCE_REQD.html))
+    /// and the counter against the policy is left to the caller.
+    pub fn verify_sk(&self, data: &[u8], sig: &[u8], flags: u8, counter: u32) -> OsshResult<bool> {
+        Ok(self
+            .verify_sk_ssh(data, sig, flags, counter)?
+            .unwrap_or(false))
+    }
+
+    // Return `None` if the signature is malformed
+    pub(crate) fn verify_sk_ssh(
+        &self,
+        data: &[u8],
+        sig: &[u8],
+        flags: u8,
+        counter: u32,
+    ) -> OsshResult<Option<bool>> {
+        let signed = sk_signed_data(&self.application, flags, counter, data);
+        self.key.verify_ssh(&signed, sig)
+    }
+}
+
+impl Key for EcDsaSkPublicKey {
+    fn size(&self) -> usize {
+        self.key.size()
+    }
+
+    fn keyname(&self) -> &'static str {
+        ECDSA_SK_NAME
+    }
+
+    fn short_keyname(&self) -> &'static str {
+        ECDSA_SK_SHORT_NAME
+    }
+}
+
+impl PublicParts for EcDsaSkPublicKey {
+    fn blob(&self) -> Result<Vec<u8>, Error> {
+        encode_ecdsa_sk_pubkey(self)
+    }
+
+    /// Verify the signature, given as the SSH encoded `mpint r, mpint s`, the flags and the big-endian signature counter
+    ///
+    /// This is the layout following the algorithm name in the SSH signature blob without the length prefix,
+    /// see [`verify_sk()`](#method.verify_sk) for the details.
+    fn verify(&self, data: &[u8], sig: &[u8]) -> Result<bool, Error> {
+        if sig.len() < 5 {
+            return Err(ErrorKind::InvalidLength.into());
+        }
+        let (sig, rest) = sig.split_at(sig.len() - 5);
+        let counter = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]);
+        self.verify_sk(data, sig, rest[0], counter)
+    }
+}
+
+impl fmt::Display for EcDsaSkPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&serialize_ossh_pubkey(self, "").unwrap())
+    }
+}
+
+/// Represent the EcDSA key pair stored in a FIDO/U2F security key (`sk-ecdsa-sha2-nistp256@openssh.com`)
+///
+/// The private key never leaves the token, the key file only stores the key handle to identify it on the token.
+/// So the signatures can't be made by this crate,
+/// [`sign()`](../trait.PrivateParts.html#tymethod.sign) returns
+/// [`ErrorKind::SecurityKeyRequired`](../../error/enum.ErrorKind.html#variant.SecurityKeyRequired).
+#[derive(Clone)]
+pub struct EcDsaSkKeyPair {
+    public: EcDsaSkPublicKey,
+    flags: u8,
+    key_handle: Vec<u8>,
+    reserved: Vec<u8>,
+}
+
+impl EcDsaSkKeyPair {
+    /// Create the key pair from the public key and the data returned by the token on enrollment
+    pub fn new(public: EcDsaSkPublicKey, flags: u8, key_handle: &[u8]) -> Self {
+        Self::from_parts(public, flags, key_handle.to_vec(), Vec::new())
+    }
+
+    pub(crate) fn from_parts(
+        public: EcDsaSkPublicKey,
+        flags: u8,
+        key_handle: Vec<u8>,
+        reserved: Vec<u8>,
+    ) -> Self {
+        Self {
+            public,
+            flags,
+            key_handle,
+            reserved,
+        }
+    }
+
+    /// The application (relying party ID) of the key, e.g. `ssh:`
+    pub fn application(&self) -> &str {
+        self.public.application()
+    }
+
+    /// The flags of the key, e.g. [`SK_USER_PRESENCE_REQD`](../constant.SK_USER_PRESENCE_REQD.html)
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// The key handle used by the token to find the private key
+    pub fn key_handle(&self) -> &[u8] {
+        &self.key_handle
+    }
+
+    /// The reserved field of the key file, which is empty for now
+    pub fn reserved(&self) -> &[u8] {
+        &self.reserved
+    }
+
+    /// Clone the public parts to generate public key
+    pub fn clone_public_key(&self) -> Result<EcDsaSkPublicKey, Error> {
+        Ok(self.public.clone())
+    }
+}
+
+impl Key for EcDsaSkKeyPair {
+    fn size(&self) -> usize {
+        self.public.size()
+    }
+
+    fn keyname(&self) -> &'static str {
+        ECDSA_SK_NAME
+    }
+
+    fn short_keyname(&self) -> &'static str {
+        ECDSA_SK_SHORT_NAME
+    }
+}
+
+impl PublicParts for EcDsaSkKeyPair {
+    fn blob(&self) -> Result<Vec<u8>, Error> {
+        self.public.blob()
+    }
+
+    fn verify(&self, data: &[u8], sig: &[u8]) -> Result<bool, Error> {
+        self.public.verify(data, sig)
+    }
+}
+
+impl PrivateParts for EcDsaSkKeyPair {
+    fn sign(&self, _data: &[u8]) -> Result<Vec<u8>, Error> {
+        Err(ErrorKind::SecurityKeyRequired.into())
+    }
+}
+
 fn into_ec_point(curve: EcCurve, public_key: &[u8]) -> Result<EcPoint, openssl::error::ErrorStack> {
     let mut bn_ctx = BigNumContext::new()?;
     let group: EcGroup = curve.try_into()?;
     EcPoint::from_bytes(&group, public_key, &mut bn_ctx)
 }
 
+/// Convert the SSH encoded ECDSA signature into the DER encoded `ECDSA-Sig-Value`
+///
+/// The input is the signature blob inside the `ecdsa-sha2-*` signature, which is `mpint r, mpint s`.
+/// The non-canonical mpints, zero values and trailing data are rejected as
+/// [`ErrorKind::InvalidArgument`](../../error/enum.ErrorKind.html#variant.InvalidArgument).
+pub fn ssh_sig_to_der(ssh_sig: &[u8]) -> OsshResult<Vec<u8>> {
+    let mut reader = io::Cursor::new(ssh_sig);
+    let mut read_part = || -> OsshResult<BigNum> {
+        let data = reader
+            .read_string()
+            .map_err(|_| Error::from_kind(ErrorKind::InvalidArgument))?;
+        if !is_canonical_mpint(&data) {
+            return Err(ErrorKind::InvalidArgument.into());
+        }
+        Ok(BigNum::from_slice(&data)?)
+    };
+    let r = read_part()?;
+    let s = read_part()?;
+    if reader.position() as usize != ssh_sig.len() {
+        return Err(ErrorKind::InvalidArgument.into());
+    }
+    Ok(EcdsaSig::from_private_components(r, s)?.to_der()?)
+}
+
+/// Convert the DER encoded `ECDSA-Sig-Value` into the SSH encoded ECDSA signature
+///
+/// The output is the signature blob inside the `ecdsa-sha2-*` signature, which is `mpint r, mpint s`.
+/// The input must be strictly canonical DER with non-zero values,
+/// or [`ErrorKind::InvalidArgument`](../../error/enum.ErrorKind.html#variant.InvalidArgument) is returned.
+pub fn der_sig_to_ssh(der: &[u8]) -> OsshResult<Vec<u8>> {
+    if !is_canonical_dsa_sig(der) {
+        return Err(ErrorKind::InvalidArgument.into());
+    }
+    let sig = EcdsaSig::from_der(der)?;
+    if sig.r().num_bits() == 0 || sig.s().num_bits() == 0 {
+        return Err(ErrorKind::InvalidArgument.into());
+    }
+    let mut buf = Vec::new();
+    buf.write_mpint(sig.r())?;
+    buf.write_mpint(sig.s())?;
+    Ok(buf)
+}
+
+// Check if the mpint is positive and minimally encoded
+fn is_canonical_mpint(data: &[u8]) -> bool {
+    match data {
+        [] => false,
+        [first, ..] if first & 0x80 != 0 => false,
+        [0] => false,
+        [0, second, ..] => second & 0x80 != 0,
+        _ => true,
+    }
+}
+
 #[allow(non_upper_case_globals)]
 #[cfg(test)]
 mod test {
@@ -350,4 +785,201 @@ mod test {
         let key = get_test_pubkey().unwrap();
         assert_eq!(key.size(), 256);
     }
+
+    #[test]
+    fn ecdsa_point_compression() {
+        let key = get_test_pubkey().unwrap();
+        assert_eq!(key.to_point_bytes(false).unwrap(), pub_key);
+        let compressed = key.to_point_bytes(true).unwrap();
+        assert_eq!(compressed.len(), 33);
+        assert_eq!(compressed[0], 0x02 | (pub_key[64] & 1));
+        assert_eq!(compressed[1..], pub_key[1..33]);
+        let decompressed =
+            EcDsaPublicKey::from_point_bytes(EcCurve::Nistp256, &compressed).unwrap();
+        assert_eq!(decompressed, key);
+        assert_eq!(decompressed.to_point_bytes(false).unwrap(), pub_key);
+
+        let mut off_curve = pub_key;
+        off_curve[64] ^= 1;
+        let mut flipped = compressed.clone();
+        flipped[0] ^= 1;
+        let flipped = EcDsaPublicKey::from_point_bytes(EcCurve::Nistp256, &flipped).unwrap();
+        assert_ne!(flipped, key);
+        for point in [&off_curve[..], &[0x00], &[], &compressed[..32]] {
+            assert_eq!(
+                EcDsaPublicKey::from_point_bytes(EcCurve::Nistp256, point)
+                    .unwrap_err()
+                    .kind(),
+                ErrorKind::InvalidKey
+            );
+        }
+        // About half of the x coordinates have no point on the curve
+        let mut invalid_x = compressed;
+        let rejected = (0..=255u8).any(|b| {
+            invalid_x[32] = b;
+            EcDsaPublicKey::from_point_bytes(EcCurve::Nistp256, &invalid_x).map_err(|e| e.kind())
+                == Err(ErrorKind::InvalidKey)
+        });
+        assert!(rejected);
+    }
+
+    #[test]
+    fn ecdsa_private_scalar_bytes() {
+        for (bits, len) in [(256, 32), (384, 48), (521, 66)] {
+            let key = EcDsaKeyPair::generate(bits).unwrap();
+            let scalar = key.private_scalar_bytes();
+            assert_eq!(scalar.len(), len);
+            assert_eq!(
+                &*BigNum::from_slice(&scalar).unwrap(),
+                key.ossl_ec().private_key()
+            );
+        }
+    }
+
+    #[test]
+    fn ecdsa_sign_ssh() {
+        use crate::sshbuf::SshReadExt;
+
+        let key = EcDsaKeyPair::generate(384).unwrap();
+        let mut reader = std::io::Cursor::new(key.sign_ssh(b"data").unwrap());
+        let r = reader.read_mpint().unwrap();
+        let s = reader.read_mpint().unwrap();
+        let sig = EcdsaSig::from_private_components(r, s).unwrap();
+        let dgst = openssl::hash::hash(MessageDigest::sha384(), b"data").unwrap();
+        assert!(sig.verify(&dgst, key.ossl_ec()).unwrap());
+    }
+
+    #[test]
+    fn ecdsa_sig_der_conversion() {
+        let key = EcDsaKeyPair::generate(521).unwrap();
+        let ssh_sig = key.sign_ssh(b"data").unwrap();
+        let der = ssh_sig_to_der(&ssh_sig).unwrap();
+        let dgst = openssl::hash::hash(MessageDigest::sha512(), b"data").unwrap();
+        assert!(EcdsaSig::from_der(&der)
+            .unwrap()
+            .verify(&dgst, key.ossl_ec())
+            .unwrap());
+        assert_eq!(der_sig_to_ssh(&der).unwrap(), ssh_sig);
+
+        // r = 0x80 with its leading zero, s = 1
+        let ssh_sig = [0, 0, 0, 2, 0x00, 0x80, 0, 0, 0, 1, 0x01];
+        let der = [0x30, 0x07, 0x02, 0x02, 0x00, 0x80, 0x02, 0x01, 0x01];
+        assert_eq!(ssh_sig_to_der(&ssh_sig).unwrap(), der);
+        assert_eq!(der_sig_to_ssh(&der).unwrap(), ssh_sig);
+
+        for bad in [
+            &[0, 0, 0, 2, 0x00, 0x01, 0, 0, 0, 1, 0x01][..], // Unneeded leading zero
+            &[0, 0, 0, 1, 0x80, 0, 0, 0, 1, 0x01],           // Negative
+            &[0, 0, 0, 0, 0, 0, 0, 1, 0x01],                 // Zero
+            &[0, 0, 0, 1, 0x01, 0, 0, 0, 1, 0x01, 0x00],     // Trailing data
+            &[0, 0, 0, 1, 0x01],                             // Truncated
+        ] {
+            assert_eq!(
+                ssh_sig_to_der(bad).unwrap_err().kind(),
+                ErrorKind::InvalidArgument
+            );
+        }
+        for bad in [
+            &[0x30, 0x07, 0x02, 0x02, 0x00, 0x01, 0x02, 0x01, 0x01][..],
+            &[0x30, 0x06, 0x02, 0x01, 0x00, 0x02, 0x01, 0x01],
+        ] {
+            assert_eq!(
+                der_sig_to_ssh(bad).unwrap_err().kind(),
+                ErrorKind::InvalidArgument
+            );
+        }
+    }
+
+    fn explicit_group(curve: EcCurve) -> EcGroup {
+        let named: EcGroup = curve.try_into().unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        let (mut p, mut a, mut b) = (
+            BigNum::new().unwrap(),
+            BigNum::new().unwrap(),
+            BigNum::new().unwrap(),
+        );
+        named
+            .components_gfp(&mut p, &mut a, &mut b, &mut ctx)
+            .unwrap();
+        let mut order = BigNum::new().unwrap();
+        let mut cofactor = BigNum::new().unwrap();
+        named.order(&mut order, &mut ctx).unwrap();
+        named.cofactor(&mut cofactor, &mut ctx).unwrap();
+        #[allow(deprecated)]
+        let generator = named
+            .generator()
+            .to_bytes(&named, PointConversionForm::UNCOMPRESSED, &mut ctx)
+            .unwrap();
+
+        let mut group = EcGroup::from_components(p, a, b, &mut ctx).unwrap();
+        let generator = EcPoint::from_bytes(&group, &generator, &mut ctx).unwrap();
+        group.set_generator(generator, order, cofactor).unwrap();
+        group
+    }
+
+    #[test]
+    fn ecdsa_explicit_group_curve() {
+        for curve in [EcCurve::Nistp256, EcCurve::Nistp384, EcCurve::Nistp521] {
+            let group = explicit_group(curve);
+            assert!(group.curve_name().is_none());
+            assert_eq!(EcCurve::from_group(&group).unwrap(), curve);
+
+            let key = EcKey::generate(&group).unwrap();
+            let keypair = EcDsaKeyPair::from_ossl_ec(key).unwrap();
+            assert_eq!(keypair.curve(), curve);
+            // Converted to the named curve
+            assert!(keypair.ossl_ec().group().curve_name().is_some());
+        }
+    }
+
+    #[test]
+    fn ecdsa_sk_verify() {
+        use crate::keys::{PublicKey, VerifyOutcome, SK_USER_PRESENCE_REQD};
+
+        // Act as the token by signing the message with a software key
+        let token = EcDsaKeyPair::generate_with_curve(EcCurve::Nistp256).unwrap();
+        let mut bn_ctx = BigNumContext::new().unwrap();
+        let point = token
+            .ossl_ec()
+            .public_key()
+            .to_bytes(
+                token.ossl_ec().group(),
+                PointConversionForm::UNCOMPRESSED,
+                &mut bn_ctx,
+            )
+            .unwrap();
+        let skkey = EcDsaSkPublicKey::new(&point, "ssh:").unwrap();
+        let (flags, counter) = (SK_USER_PRESENCE_REQD, 7u32);
+        let signed = sk_signed_data("ssh:", flags, counter, b"data");
+        let sig = token.sign_ssh(&signed).unwrap();
+
+        assert!(skkey.verify_sk(b"data", &sig, flags, counter).unwrap());
+        assert!(!skkey.verify_sk(b"data", &sig, 0, counter).unwrap());
+        assert!(!skkey.verify_sk(b"other", &sig, flags, counter).unwrap());
+
+        let mut blob = Vec::new();
+        blob.write_utf8(ECDSA_SK_NAME).unwrap();
+        blob.write_string(&sig).unwrap();
+        blob.push(flags);
+        blob.extend_from_slice(&counter.to_be_bytes());
+        let pubkey = PublicKey::from(skkey.clone());
+        assert_eq!(
+            pubkey.verify_detailed(b"data", &blob).unwrap(),
+            VerifyOutcome::Valid
+        );
+        assert_eq!(
+            pubkey.verify_detailed(b"other", &blob).unwrap(),
+            VerifyOutcome::Invalid
+        );
+        assert_eq!(
+            pubkey
+                .verify_detailed(b"data", &blob[..blob.len() - 4])
+                .unwrap(),
+            VerifyOutcome::MalformedSignature
+        );
+        let sig_start = blob.len() - 5 - sig.len();
+        assert!(skkey.verify(b"data", &blob[sig_start..]).unwrap());
+        let decoded = PublicKey::from_blob(&pubkey.blob().unwrap()).unwrap();
+        assert_eq!(decoded.as_ecdsa_sk(), Some(&skkey));
+    }
 }
@@ -1,4 +1,4 @@
-use super::{Key, PrivateParts, PublicParts};
+use super::{sk_signed_data, Key, PrivateParts, PublicParts};
 use crate::error::{Error, ErrorKind, OsshResult};
 use crate::format::ossh_pubkey::*;
 use openssl::pkey::{Id, PKey, Private, Public};
@@ -9,15 +9,24 @@ use ed25519_dalek::{
     Signature,
     Signer,
     Verifier,
+    KEYPAIR_LENGTH,
     PUBLIC_KEY_LENGTH,
+    SECRET_KEY_LENGTH,
+    SIGNATURE_LENGTH,
 };
 use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 use std::fmt;
+use zeroize::Zeroizing;
 
 /// The key name returned by [`Key::keyname()`](../trait.Key.html#method.keyname)
 pub const ED25519_NAME: &str = "ssh-ed25519";
 /// The short key name returned by [`Key::short_keyname()`](../trait.Key.html#method.short_keyname)
 pub const ED25519_SHORT_NAME: &str = "ED25519";
+/// The key name of the FIDO/U2F security key returned by [`Key::keyname()`](../trait.Key.html#method.keyname)
+pub const ED25519_SK_NAME: &str = "sk-ssh-ed25519@openssh.com";
+/// The short key name of the FIDO/U2F security key returned by [`Key::short_keyname()`](../trait.Key.html#method.short_keyname)
+pub const ED25519_SK_SHORT_NAME: &str = "ED25519-SK";
 
 /// Represent the Ed25519 public key
 #[derive(Debug, Clone)]
@@ -42,6 +51,19 @@ impl Ed25519PublicKey {
     pub(crate) fn ossl_pkey(&self) -> Result<PKey<Public>, openssl::error::ErrorStack> {
         PKey::public_key_from_raw_bytes(self.key.as_bytes(), Id::ED25519)
     }
+
+    pub(crate) fn as_bytes(&self) -> &[u8; PUBLIC_KEY_LENGTH] {
+        self.key.as_bytes()
+    }
+
+    /// Convert to the X25519 public key
+    ///
+    /// The Montgomery u-coordinate is derived from the Edwards point by the birational map `u = (1 + y) / (1 - y)`,
+    /// which is the same conversion used by age's `ssh-ed25519` recipients and libsodium's
+    /// `crypto_sign_ed25519_pk_to_curve25519()`.
+    pub fn to_x25519(&self) -> [u8; 32] {
+        self.key.to_montgomery().to_bytes()
+    }
 }
 
 impl Key for Ed25519PublicKey {
@@ -82,6 +104,7 @@ impl fmt::Display for Ed25519PublicKey {
 }
 
 /// Represent the Ed25519 key pair
+#[derive(Clone)]
 pub struct Ed25519KeyPair {
     pub(crate) key: Box<SigningKey>,
 }
@@ -105,19 +128,33 @@ impl Ed25519KeyPair {
     ///
     /// The bits parameter should be 256 bits or `0` to use default length (256 bits).
     pub fn generate(bits: usize) -> OsshResult<Self> {
+        Self::generate_with_rng(bits, &mut OsRng)
+    }
+
+    /// Generate Ed25519 key pair with the given RNG
+    ///
+    /// The key is derived only from the RNG output, so a seeded RNG generates the same key.
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(bits: usize, rng: &mut R) -> OsshResult<Self> {
         if bits != 0 && bits != 256 {
             return Err(Error::from_kind(ErrorKind::InvalidKeySize));
         }
 
         Ok(Ed25519KeyPair {
-            key: Box::new(SigningKey::generate(&mut OsRng)),
+            key: Box::new(SigningKey::generate(rng)),
         })
     }
 
+    /// Load the keypair from the public key and the 64 bytes secret (seed || public key)
+    ///
+    /// Both the public key and the public half of the secret must match the one derived from the seed,
+    /// otherwise `ErrorKind::InvalidKey` is returned.
     pub(crate) fn from_bytes(pk: &[u8], sk: &[u8]) -> OsshResult<Self> {
-        let verify_key = VerifyingKey::try_from(pk)?;
-        let secret_key = SigningKey::from_keypair_bytes(sk.try_into()?)?;
-        if secret_key.verifying_key() != verify_key {
+        if pk.len() != PUBLIC_KEY_LENGTH || sk.len() != KEYPAIR_LENGTH {
+            return Err(ErrorKind::InvalidLength.into());
+        }
+        let secret_key = SigningKey::from_bytes(sk[..SECRET_KEY_LENGTH].try_into()?);
+        let derived = secret_key.verifying_key();
+        if derived.as_bytes() != pk || derived.as_bytes() != &sk[SECRET_KEY_LENGTH..] {
             return Err(ErrorKind::InvalidKey.into());
         }
         Ok(Ed25519KeyPair {
@@ -125,6 +162,50 @@ impl Ed25519KeyPair {
         })
     }
 
+    /// Export the 64 bytes secret key used by libsodium and NaCl `crypto_sign`
+    ///
+    /// The layout is the 32 bytes seed followed by the 32 bytes public key, both as raw bytes.
+    /// It is not the expanded secret scalar, which some other Ed25519 encodings store instead.
+    /// The secret key is cleared from the memory when the returned value is dropped.
+    pub fn to_libsodium_secret(&self) -> Zeroizing<[u8; KEYPAIR_LENGTH]> {
+        Zeroizing::new(self.key.to_keypair_bytes())
+    }
+
+    /// Import the 64 bytes secret key used by libsodium and NaCl `crypto_sign`
+    ///
+    /// The layout is the same as [`to_libsodium_secret()`](#method.to_libsodium_secret).
+    /// The public half must match the one derived from the seed, otherwise `ErrorKind::InvalidKey` is returned.
+    pub fn from_libsodium_secret(secret: &[u8; KEYPAIR_LENGTH]) -> OsshResult<Self> {
+        Self::from_bytes(&secret[SECRET_KEY_LENGTH..], secret)
+    }
+
+    /// Export the 32 bytes seed, which is the Ed25519 private key defined by RFC 8032
+    ///
+    /// The seed is cleared from the memory when the returned value is dropped.
+    ///
+    /// This is an advanced API for e.g. the HSM import or the key backup.
+    /// Both the signing scalar and the nonce prefix are derived from the seed,
+    /// so a backup of it must be kept as safe as the private key file.
+    pub fn seed(&self) -> Zeroizing<[u8; SECRET_KEY_LENGTH]> {
+        Zeroizing::new(self.key.to_bytes())
+    }
+
+    /// Convert to the X25519 private key
+    ///
+    /// The scalar is the first 32 bytes of the SHA-512 hash of the seed, clamped as RFC 7748 specifies.
+    /// It is the same scalar used by the Ed25519 signing,
+    /// so the result matches [`Ed25519PublicKey::to_x25519()`](struct.Ed25519PublicKey.html#method.to_x25519)
+    /// of the public key.
+    /// The scalar is cleared from the memory when the returned value is dropped.
+    pub fn to_x25519(&self) -> Zeroizing<[u8; 32]> {
+        // The first half of SHA-512(seed), unclamped
+        let mut scalar = Zeroizing::new(self.key.to_scalar_bytes());
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+        scalar
+    }
+
     /// Clone the public parts to generate public key
     pub fn clone_public_key(&self) -> Result<Ed25519PublicKey, Error> {
         Ok(Ed25519PublicKey {
@@ -132,9 +213,10 @@ impl Ed25519KeyPair {
         })
     }
 
-    pub(crate) fn from_ossl_ed25519(key: &[u8]) -> Result<Self, Error> {
+    // OpenSSL stores the 32 bytes seed as the raw private key
+    pub(crate) fn from_ossl_ed25519(seed: &[u8]) -> Result<Self, Error> {
         Ok(Self {
-            key: Box::new(SigningKey::from_keypair_bytes(key.try_into()?)?),
+            key: Box::new(SigningKey::from_bytes(seed.try_into()?)),
         })
     }
 
@@ -160,6 +242,178 @@ impl PrivateParts for Ed25519KeyPair {
     }
 }
 
+/// Represent the Ed25519 public key of a FIDO/U2F security key (`sk-ssh-ed25519@openssh.com`)
+///
+/// The key stores the application (relying party ID, usually `ssh:`) besides the Ed25519 public key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ed25519SkPublicKey {
+    key: Ed25519PublicKey,
+    application: String,
+}
+
+impl Ed25519SkPublicKey {
+    /// Create the public key from the Ed25519 public key and the application
+    pub fn new(
+        key: &[u8; PUBLIC_KEY_LENGTH],
+        application: &str,
+    ) -> Result<Self, ed25519_dalek::SignatureError> {
+        Ok(Self {
+            key: Ed25519PublicKey::new(key)?,
+            application: application.to_owned(),
+        })
+    }
+
+    /// The application (relying party ID) of the key, e.g. `ssh:`
+    pub fn application(&self) -> &str {
+        &self.application
+    }
+
+    /// The Ed25519 public key of the token
+    pub fn ed25519_key(&self) -> &Ed25519PublicKey {
+        &self.key
+    }
+
+    /// Verify the signature made by the security key
+    ///
+    /// The token signs `SHA256(application) || flags || counter || SHA256(data)`,
+    /// so the flags and the signature counter returned with the signature are also required.
+    /// Checking the flags (e.g. [`SK_USER_PRESENCE_REQD`](../constant.SK_USER_PRESENCE_REQD.html))
+    /// and the counter against the policy is left to the caller.
+    pub fn verify_sk(&self, data: &[u8], sig: &[u8], flags: u8, counter: u32) -> OsshResult<bool> {
+        let signed = sk_signed_data(&self.application, flags, counter, data);
+        self.key.verify(&signed, sig)
+    }
+}
+
+impl Key for Ed25519SkPublicKey {
+    fn size(&self) -> usize {
+        256
+    }
+
+    fn keyname(&self) -> &'static str {
+        ED25519_SK_NAME
+    }
+
+    fn short_keyname(&self) -> &'static str {
+        ED25519_SK_SHORT_NAME
+    }
+}
+
+impl PublicParts for Ed25519SkPublicKey {
+    fn blob(&self) -> Result<Vec<u8>, Error> {
+        encode_ed25519_sk_pubkey(self)
+    }
+
+    /// Verify the signature, given as the 64 bytes signature, the flags and the big-endian signature counter
+    ///
+    /// This is the layout following the algorithm name in the SSH signature blob without the length prefix,
+    /// see [`verify_sk()`](#method.verify_sk) for the details.
+    fn verify(&self, data: &[u8], sig: &[u8]) -> Result<bool, Error> {
+        if sig.len() != SIGNATURE_LENGTH + 5 {
+            return Err(ErrorKind::InvalidLength.into());
+        }
+        let (sig, rest) = sig.split_at(SIGNATURE_LENGTH);
+        let counter = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]);
+        self.verify_sk(data, sig, rest[0], counter)
+    }
+}
+
+impl fmt::Display for Ed25519SkPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&serialize_ossh_pubkey(self, "").unwrap())
+    }
+}
+
+/// Represent the Ed25519 key pair stored in a FIDO/U2F security key (`sk-ssh-ed25519@openssh.com`)
+///
+/// The private key never leaves the token, the key file only stores the key handle to identify it on the token.
+/// So the signatures can't be made by this crate,
+/// [`sign()`](../trait.PrivateParts.html#tymethod.sign) returns
+/// [`ErrorKind::SecurityKeyRequired`](../../error/enum.ErrorKind.html#variant.SecurityKeyRequired).
+#[derive(Clone)]
+pub struct Ed25519SkKeyPair {
+    public: Ed25519SkPublicKey,
+    flags: u8,
+    key_handle: Vec<u8>,
+    reserved: Vec<u8>,
+}
+
+impl Ed25519SkKeyPair {
+    /// Create the key pair from the public key and the data returned by the token on enrollment
+    pub fn new(public: Ed25519SkPublicKey, flags: u8, key_handle: &[u8]) -> Self {
+        Self::from_parts(public, flags, key_handle.to_vec(), Vec::new())
+    }
+
+    pub(crate) fn from_parts(
+        public: Ed25519SkPublicKey,
+        flags: u8,
+        key_handle: Vec<u8>,
+        reserved: Vec<u8>,
+    ) -> Self {
+        Self {
+            public,
+            flags,
+            key_handle,
+            reserved,
+        }
+    }
+
+    /// The application (relying party ID) of the key, e.g. `ssh:`
+    pub fn application(&self) -> &str {
+        self.public.application()
+    }
+
+    /// The flags of the key, e.g. [`SK_USER_PRESENCE_REQD`](../constant.SK_USER_PRESENCE_REQD.html)
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// The key handle used by the token to find the private key
+    pub fn key_handle(&self) -> &[u8] {
+        &self.key_handle
+    }
+
+    /// The reserved field of the key file, which is empty for now
+    pub fn reserved(&self) -> &[u8] {
+        &self.reserved
+    }
+
+    /// Clone the public parts to generate public key
+    pub fn clone_public_key(&self) -> Result<Ed25519SkPublicKey, Error> {
+        Ok(self.public.clone())
+    }
+}
+
+impl Key for Ed25519SkKeyPair {
+    fn size(&self) -> usize {
+        256
+    }
+
+    fn keyname(&self) -> &'static str {
+        ED25519_SK_NAME
+    }
+
+    fn short_keyname(&self) -> &'static str {
+        ED25519_SK_SHORT_NAME
+    }
+}
+
+impl PublicParts for Ed25519SkKeyPair {
+    fn blob(&self) -> Result<Vec<u8>, Error> {
+        self.public.blob()
+    }
+
+    fn verify(&self, data: &[u8], sig: &[u8]) -> Result<bool, Error> {
+        self.public.verify(data, sig)
+    }
+}
+
+impl PrivateParts for Ed25519SkKeyPair {
+    fn sign(&self, _data: &[u8]) -> Result<Vec<u8>, Error> {
+        Err(ErrorKind::SecurityKeyRequired.into())
+    }
+}
+
 #[allow(non_upper_case_globals)]
 #[cfg(test)]
 mod test {
@@ -188,4 +442,130 @@ mod test {
         let key = get_test_pubkey().unwrap();
         assert_eq!(key.size(), 256);
     }
+
+    #[test]
+    fn ed25519_from_bytes_mismatch() {
+        let key = Ed25519KeyPair::generate(256).unwrap();
+        let pk = key.key.verifying_key().to_bytes();
+        let sk = key.key.to_keypair_bytes();
+        assert!(Ed25519KeyPair::from_bytes(&pk, &sk).is_ok());
+
+        let other = Ed25519KeyPair::generate(256).unwrap();
+        let other_pk = other.key.verifying_key().to_bytes();
+        let err = Ed25519KeyPair::from_bytes(&other_pk, &sk).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidKey);
+
+        let mut bad_sk = sk;
+        bad_sk[SECRET_KEY_LENGTH..].copy_from_slice(&other_pk);
+        let err = Ed25519KeyPair::from_bytes(&pk, &bad_sk).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidKey);
+
+        let err = Ed25519KeyPair::from_bytes(&pk, &sk[..SECRET_KEY_LENGTH])
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidLength);
+    }
+
+    #[test]
+    fn ed25519_libsodium_secret() {
+        // RFC 8032 section 7.1 test 1
+        let seed =
+            hex_literal::hex!("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60");
+        let public =
+            hex_literal::hex!("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+        let mut secret = [0u8; KEYPAIR_LENGTH];
+        secret[..SECRET_KEY_LENGTH].copy_from_slice(&seed);
+        secret[SECRET_KEY_LENGTH..].copy_from_slice(&public);
+
+        let key = Ed25519KeyPair::from_libsodium_secret(&secret).unwrap();
+        assert_eq!(key.key.verifying_key().to_bytes(), public);
+        assert_eq!(*key.to_libsodium_secret(), secret);
+
+        secret[KEYPAIR_LENGTH - 1] ^= 1;
+        let err = Ed25519KeyPair::from_libsodium_secret(&secret)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidKey);
+    }
+
+    #[test]
+    fn ed25519_seed() {
+        let key = Ed25519KeyPair::generate(256).unwrap();
+        let seed = key.seed();
+        assert_eq!(*seed, key.to_libsodium_secret()[..SECRET_KEY_LENGTH]);
+        let reloaded = Ed25519KeyPair::from_ossl_ed25519(&*seed).unwrap();
+        assert_eq!(*reloaded.to_libsodium_secret(), *key.to_libsodium_secret());
+    }
+
+    #[test]
+    fn ed25519_to_x25519() {
+        use sha2::{Digest, Sha512};
+
+        let key = Ed25519KeyPair::generate(256).unwrap();
+        let scalar = key.to_x25519();
+        let hash = Sha512::digest(key.key.to_bytes());
+        assert_eq!(scalar[1..31], hash[1..31]);
+        assert_eq!(scalar[0] & 7, 0);
+        assert_eq!(scalar[31] & 0xc0, 0x40);
+
+        // The X25519 public key of the scalar must match the converted public key
+        let x25519 = PKey::private_key_from_raw_bytes(&scalar[..], Id::X25519).unwrap();
+        let pubkey = key.clone_public_key().unwrap();
+        assert_eq!(x25519.raw_public_key().unwrap(), pubkey.to_x25519());
+    }
+
+    #[test]
+    fn ed25519_sk_verify() {
+        use crate::keys::{PublicKey, VerifyOutcome, SK_USER_PRESENCE_REQD};
+        use crate::sshbuf::SshWriteExt;
+        use sha2::{Digest, Sha256};
+
+        // Act as the token by signing the message with a software key
+        let token = Ed25519KeyPair::generate(256).unwrap();
+        let pk = token.key.verifying_key().to_bytes();
+        let skkey = Ed25519SkPublicKey::new(&pk, "ssh:").unwrap();
+        let (flags, counter) = (SK_USER_PRESENCE_REQD, 42u32);
+        let mut signed = Sha256::digest(b"ssh:").to_vec();
+        signed.push(flags);
+        signed.extend_from_slice(&counter.to_be_bytes());
+        signed.extend_from_slice(&Sha256::digest(b"data"));
+        let sig = token.sign(&signed).unwrap();
+
+        assert!(skkey.verify_sk(b"data", &sig, flags, counter).unwrap());
+        assert!(!skkey.verify_sk(b"data", &sig, flags, counter + 1).unwrap());
+        assert!(!skkey.verify_sk(b"other", &sig, flags, counter).unwrap());
+
+        let mut raw = sig.clone();
+        raw.push(flags);
+        raw.extend_from_slice(&counter.to_be_bytes());
+        assert!(skkey.verify(b"data", &raw).unwrap());
+        assert_eq!(
+            skkey.verify(b"data", &sig).unwrap_err().kind(),
+            ErrorKind::InvalidLength
+        );
+
+        let mut blob = Vec::new();
+        blob.write_utf8(ED25519_SK_NAME).unwrap();
+        blob.write_string(&sig).unwrap();
+        blob.push(flags);
+        blob.extend_from_slice(&counter.to_be_bytes());
+        let pubkey = PublicKey::from(skkey);
+        assert_eq!(
+            pubkey.verify_detailed(b"data", &blob).unwrap(),
+            VerifyOutcome::Valid
+        );
+        assert_eq!(
+            pubkey
+                .verify_detailed(b"data", &blob[..blob.len() - 1])
+                .unwrap(),
+            VerifyOutcome::MalformedSignature
+        );
+        assert_eq!(pubkey.blob().unwrap(), {
+            let mut expected = Vec::new();
+            expected.write_utf8(ED25519_SK_NAME).unwrap();
+            expected.write_string(&pk).unwrap();
+            expected.write_utf8("ssh:").unwrap();
+            expected
+        });
+    }
 }
@@ -1,16 +1,23 @@
 use super::{Key, PrivateParts, PublicParts};
 use crate::error::{Error, ErrorKind, OsshResult};
 use crate::format::ossh_pubkey::*;
-use openssl::bn::BigNum;
+use crate::sshbuf::SecretBigNum;
+use foreign_types::ForeignType;
+use openssl::bn::{BigNum, BigNumContext, BigNumRef};
+use openssl::error::ErrorStack;
 use openssl::hash::MessageDigest;
 use openssl::pkey::{PKey, Private, Public};
-use openssl::rsa::{Rsa, RsaRef};
-use openssl::sign::{Signer, Verifier};
+use openssl::rsa::{Padding, Rsa, RsaRef};
+use openssl::sign::{RsaPssSaltlen, Signer, Verifier};
 use std::fmt;
+use std::ops::ControlFlow;
+use std::os::raw::{c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
 
-const RSA_DEF_SIZE: usize = 2048;
+// The same as ssh-keygen
+pub(crate) const RSA_DEF_SIZE: usize = 3072;
 const RSA_MIN_SIZE: usize = 1024;
-const RSA_MAX_SIZE: usize = 16384;
+pub(crate) const RSA_MAX_SIZE: usize = 16384;
 /// The default name of RSA key returned by [`Key::keyname()`](../trait.Key.html#method.keyname)
 pub const RSA_NAME: &str = "ssh-rsa";
 /// The sha2-256 algorithm name of RSA key returned by [`Key::keyname()`](../trait.Key.html#method.keyname)
@@ -90,8 +97,9 @@ impl RsaPublicKey {
     }
 
     pub(crate) fn from_ossl_rsa(key: Rsa<Public>, signhash: RsaSignature) -> OsshResult<Self> {
+        check_rsa_modulus(key.n(), RSA_MAX_SIZE)?;
         let rsa = Self { rsa: key, signhash };
-        if rsa.size() >= RSA_MIN_SIZE && rsa.size() <= RSA_MAX_SIZE {
+        if rsa.size() >= RSA_MIN_SIZE {
             Ok(rsa)
         } else {
             Err(ErrorKind::InvalidKeySize.into())
@@ -108,6 +116,45 @@ impl RsaPublicKey {
         self.signhash = sig;
     }
 
+    /// Verify the RSASSA-PSS signature with the given hash and salt length
+    ///
+    /// The hash is used for both the message digest and MGF1, as is common in practice.
+    /// The salt length is usually the hash length, e.g. `32` for SHA2-256, or `0` for the deterministic signatures.
+    /// Pass `-1` to recover the salt length from the signature, accepting any valid length.
+    /// The other negative lengths are rejected with `ErrorKind::InvalidArgument`.
+    ///
+    /// Unlike [`verify()`](../trait.PublicParts.html#tymethod.verify), the signature hash of the key is not used,
+    /// since PSS is not a part of the SSH signature algorithms.
+    pub fn verify_pss(
+        &self,
+        data: &[u8],
+        sig: &[u8],
+        hash: RsaSignature,
+        salt_len: i32,
+    ) -> OsshResult<bool> {
+        if self.size() < RSA_MIN_SIZE {
+            return Err(ErrorKind::InvalidKeySize.into());
+        }
+        let salt_len = match salt_len {
+            // Verifying with `RSA_PSS_SALTLEN_MAX` means recovering the salt length
+            -1 => RsaPssSaltlen::MAXIMUM_LENGTH,
+            len if len >= 0 => RsaPssSaltlen::custom(len),
+            len => {
+                return Err(Error::with_failure(
+                    ErrorKind::InvalidArgument,
+                    format!("PSS salt length {}", len),
+                ))
+            }
+        };
+        let pkey = PKey::from_rsa(self.rsa.clone())?;
+        let mut veri = Verifier::new(hash.get_digest(), &pkey)?;
+        veri.set_rsa_padding(Padding::PKCS1_PSS)?;
+        veri.set_rsa_pss_saltlen(salt_len)?;
+        veri.set_rsa_mgf1_md(hash.get_digest())?;
+        veri.update(data)?;
+        Ok(veri.verify(sig)?)
+    }
+
     pub(crate) fn ossl_rsa(&self) -> &RsaRef<Public> {
         &self.rsa
     }
@@ -155,7 +202,78 @@ impl fmt::Display for RsaPublicKey {
     }
 }
 
+/// The progress reported by [`RsaKeyPair::generate_with_callback()`](struct.RsaKeyPair.html#method.generate_with_callback)
+///
+/// The values are passed from OpenSSL's `BN_GENCB` callback, see `BN_generate_prime_ex(3)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenProgress {
+    /// The stage of the generation
+    ///
+    /// - `0`: A potential prime is generated
+    /// - `1`: A primality test round is finished
+    /// - `2`: A prime is found
+    /// - `3`: One of the two RSA primes is accepted
+    pub stage: i32,
+    /// The counter of the stage, e.g. the number of the potential primes or the test rounds
+    pub count: i32,
+}
+
+struct GenCallbackState<'a> {
+    callback: &'a mut dyn FnMut(GenProgress) -> ControlFlow<()>,
+    cancelled: bool,
+    panic: Option<Box<dyn std::any::Any + Send>>,
+}
+
+// These are not provided by openssl-sys
+extern "C" {
+    fn BN_GENCB_new() -> *mut openssl_sys::BN_GENCB;
+    fn BN_GENCB_free(cb: *mut openssl_sys::BN_GENCB);
+    fn BN_GENCB_set(
+        gencb: *mut openssl_sys::BN_GENCB,
+        callback: extern "C" fn(c_int, c_int, *mut openssl_sys::BN_GENCB) -> c_int,
+        cb_arg: *mut c_void,
+    );
+    fn BN_GENCB_get_arg(cb: *mut openssl_sys::BN_GENCB) -> *mut c_void;
+}
+
+extern "C" fn gen_callback_trampoline(
+    stage: c_int,
+    count: c_int,
+    cb: *mut openssl_sys::BN_GENCB,
+) -> c_int {
+    let state = unsafe { &mut *(BN_GENCB_get_arg(cb) as *mut GenCallbackState) };
+    if state.cancelled || state.panic.is_some() {
+        return 0;
+    }
+    let progress = GenProgress { stage, count };
+    // Unwinding across the FFI boundary is undefined behavior
+    match panic::catch_unwind(AssertUnwindSafe(|| (state.callback)(progress))) {
+        Ok(ControlFlow::Continue(())) => 1,
+        Ok(ControlFlow::Break(())) => {
+            state.cancelled = true;
+            0
+        }
+        Err(payload) => {
+            state.panic = Some(payload);
+            0
+        }
+    }
+}
+
+// Reject the modulus larger than the limit, before any expensive operation is done with it
+pub(crate) fn check_rsa_modulus(n: &BigNumRef, max_bits: usize) -> OsshResult<()> {
+    let bits = n.num_bits() as usize;
+    if bits > max_bits {
+        return Err(Error::with_failure(
+            ErrorKind::KeyTooLarge,
+            format!("{} bits RSA key", bits),
+        ));
+    }
+    Ok(())
+}
+
 /// Represent the RSA key pair
+#[derive(Clone)]
 pub struct RsaKeyPair {
     rsa: Rsa<Private>,
     signhash: RsaSignature,
@@ -163,21 +281,67 @@ pub struct RsaKeyPair {
 
 impl RsaKeyPair {
     pub(crate) fn from_ossl_rsa(key: Rsa<Private>, signhash: RsaSignature) -> OsshResult<Self> {
+        check_rsa_modulus(key.n(), RSA_MAX_SIZE)?;
         let rsa = Self { rsa: key, signhash };
-        if rsa.size() >= RSA_MIN_SIZE && rsa.size() <= RSA_MAX_SIZE {
+        if rsa.size() >= RSA_MIN_SIZE {
             Ok(rsa)
         } else {
             Err(ErrorKind::InvalidKeySize.into())
         }
     }
 
+    // Build the key from the parts stored in the key files, computing the CRT exponents
+    //
+    // The secret parts are cleared if an error occurs before OpenSSL owns them.
+    pub(crate) fn from_secret_parts(
+        n: BigNum,
+        e: BigNum,
+        d: SecretBigNum,
+        p: SecretBigNum,
+        q: SecretBigNum,
+        iqmp: SecretBigNum,
+        signhash: RsaSignature,
+    ) -> OsshResult<Self> {
+        // Computing the CRT exponents of a huge key is expensive
+        check_rsa_modulus(&n, RSA_MAX_SIZE)?;
+        let one = BigNum::from_u32(1)?;
+        let mut ctx = BigNumContext::new_secure()?;
+        // Reject the crafted primes first, the BigNum operators panic on e.g. dividing by zero when a prime is 1
+        let mut pq = BigNum::new()?;
+        pq.checked_mul(&p, &q, &mut ctx)?;
+        if *p <= *one || *q <= *one || pq != n {
+            return Err(ErrorKind::InvalidKeyFormat.into());
+        }
+        let mut crt_exponent = |prime: &BigNumRef| -> OsshResult<SecretBigNum> {
+            let mut prime1 = BigNum::new_secure()?;
+            prime1.checked_sub(prime, &one)?;
+            let prime1 = SecretBigNum::new(prime1);
+            let mut exponent = BigNum::new_secure()?;
+            exponent.checked_rem(&d, &prime1, &mut ctx)?;
+            Ok(SecretBigNum::new(exponent))
+        };
+        let dmp1 = crt_exponent(&p)?;
+        let dmq1 = crt_exponent(&q)?;
+        let rsa = Rsa::from_private_components(
+            n,
+            e,
+            d.into_inner(),
+            p.into_inner(),
+            q.into_inner(),
+            dmp1.into_inner(),
+            dmq1.into_inner(),
+            iqmp.into_inner(),
+        )?;
+        Self::from_ossl_rsa(rsa, signhash)
+    }
+
     pub(crate) fn ossl_rsa(&self) -> &RsaRef<Private> {
         &self.rsa
     }
 
     /// Generate RSA key pair
     ///
-    /// The bits parameter should be within 1024 ~ 16384 bits or `0` to use default length (2048 bits).
+    /// The bits parameter should be within 1024 ~ 16384 bits or `0` to use default length (3072 bits).
     pub fn generate(mut bits: usize) -> OsshResult<Self> {
         if bits == 0 {
             bits = RSA_DEF_SIZE;
@@ -191,6 +355,67 @@ impl RsaKeyPair {
         })
     }
 
+    /// Generate RSA key pair, reporting the progress to the callback
+    ///
+    /// The bits parameter is the same as [`generate()`](struct.RsaKeyPair.html#method.generate).
+    /// The callback is called repeatedly while OpenSSL is searching the primes,
+    /// returning [`ControlFlow::Break`](https://doc.rust-lang.org/std/ops/enum.ControlFlow.html#variant.Break)
+    /// cancels the generation with [`ErrorKind::Cancelled`](../../error/enum.ErrorKind.html#variant.Cancelled).
+    pub fn generate_with_callback<F>(mut bits: usize, mut callback: F) -> OsshResult<Self>
+    where
+        F: FnMut(GenProgress) -> ControlFlow<()>,
+    {
+        if bits == 0 {
+            bits = RSA_DEF_SIZE;
+        }
+        if !(RSA_MIN_SIZE..=RSA_MAX_SIZE).contains(&bits) {
+            return Err(Error::from_kind(ErrorKind::InvalidKeySize));
+        }
+
+        let mut state = GenCallbackState {
+            callback: &mut callback,
+            cancelled: false,
+            panic: None,
+        };
+        let e = BigNum::from_u32(65537)?;
+        let ret = unsafe {
+            let gencb = BN_GENCB_new();
+            if gencb.is_null() {
+                return Err(ErrorStack::get().into());
+            }
+            BN_GENCB_set(
+                gencb,
+                gen_callback_trampoline,
+                &mut state as *mut GenCallbackState as *mut c_void,
+            );
+            let rsa = openssl_sys::RSA_new();
+            if rsa.is_null() {
+                BN_GENCB_free(gencb);
+                return Err(ErrorStack::get().into());
+            }
+            let ret = openssl_sys::RSA_generate_key_ex(rsa, bits as c_int, e.as_ptr(), gencb);
+            BN_GENCB_free(gencb);
+            if ret == 1 {
+                Ok(Rsa::from_ptr(rsa))
+            } else {
+                openssl_sys::RSA_free(rsa);
+                Err(ErrorStack::get())
+            }
+        };
+
+        if let Some(payload) = state.panic {
+            panic::resume_unwind(payload);
+        }
+        match ret {
+            Ok(rsa) => Ok(RsaKeyPair {
+                rsa,
+                signhash: RsaSignature::default(),
+            }),
+            Err(_) if state.cancelled => Err(ErrorKind::Cancelled.into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Get the signature hash type
     pub fn sign_type(&self) -> RsaSignature {
         self.signhash
@@ -0,0 +1,131 @@
+use super::{Key, PublicParts};
+use crate::error::{Error, ErrorKind, OsshResult};
+use crate::format::ossh_pubkey::*;
+use std::fmt;
+
+/// The key name returned by [`Key::keyname()`](../trait.Key.html#method.keyname)
+pub const XMSS_NAME: &str = "ssh-xmss@openssh.com";
+/// The short key name returned by [`Key::short_keyname()`](../trait.Key.html#method.short_keyname)
+pub const XMSS_SHORT_NAME: &str = "XMSS";
+
+/// Represent the XMSS public key
+///
+/// XMSS is a stateful hash-based signature scheme, which is experimentally supported by OpenSSH.
+/// The key can only be parsed, serialized in the OpenSSH format and fingerprinted.
+/// Verifying the signatures is not supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmssPublicKey {
+    params: String,
+    key: Vec<u8>,
+}
+
+impl XmssPublicKey {
+    /// Create the XMSS public key from the parameter set name and the raw public key
+    ///
+    /// The raw public key is `OID || root || public seed` as defined in
+    /// [RFC 8391](https://tools.ietf.org/html/rfc8391#appendix-C).
+    pub fn new(params: &str, key: &[u8]) -> OsshResult<Self> {
+        if key.len() < 4 {
+            return Err(ErrorKind::InvalidKeySize.into());
+        }
+        Ok(Self {
+            params: params.to_owned(),
+            key: key.to_vec(),
+        })
+    }
+
+    /// The name of the parameter set, e.g. `XMSS_SHA2-256_W16_H10`
+    pub fn params(&self) -> &str {
+        &self.params
+    }
+
+    /// The OID of the parameter set stored in the public key
+    pub fn oid(&self) -> u32 {
+        u32::from_be_bytes([self.key[0], self.key[1], self.key[2], self.key[3]])
+    }
+
+    /// The raw public key, including the OID
+    pub fn raw_key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+impl Key for XmssPublicKey {
+    fn size(&self) -> usize {
+        // Same as OpenSSH
+        256
+    }
+
+    fn keyname(&self) -> &'static str {
+        XMSS_NAME
+    }
+
+    fn short_keyname(&self) -> &'static str {
+        XMSS_SHORT_NAME
+    }
+}
+
+impl PublicParts for XmssPublicKey {
+    fn blob(&self) -> Result<Vec<u8>, Error> {
+        encode_xmss_pubkey(self)
+    }
+
+    fn verify(&self, _data: &[u8], _sig: &[u8]) -> Result<bool, Error> {
+        Err(ErrorKind::UnsupportType.into())
+    }
+}
+
+impl fmt::Display for XmssPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&serialize_ossh_pubkey(self, "").unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys::{FingerprintHash, KeyType, PublicKey};
+    use crate::sshbuf::SshWriteExt;
+    use base64::prelude::*;
+
+    const PARAMS: &str = "XMSS_SHA2-256_W16_H10";
+
+    fn test_blob() -> Vec<u8> {
+        let mut raw = vec![0, 0, 0, 1];
+        raw.extend((0..64).map(|i| i as u8));
+        let mut blob = Vec::new();
+        blob.write_utf8(XMSS_NAME).unwrap();
+        blob.write_utf8(PARAMS).unwrap();
+        blob.write_string(&raw).unwrap();
+        blob
+    }
+
+    #[test]
+    fn xmss_publickey_parse() {
+        let blob = test_blob();
+        let keystr = format!("{} {} xmss-test", XMSS_NAME, BASE64_STANDARD.encode(&blob));
+        let pubkey = PublicKey::from_keystr(&keystr).unwrap();
+        assert_eq!(pubkey.keytype(), KeyType::XMSS);
+        assert!(pubkey.is_xmss());
+        assert_eq!(pubkey.comment(), "xmss-test");
+        assert_eq!(pubkey.serialize().unwrap(), keystr);
+        assert_eq!(pubkey.blob().unwrap(), blob);
+        assert_eq!(pubkey.size(), 256);
+        assert!(pubkey.fingerprint(FingerprintHash::SHA256).is_ok());
+
+        let xmss = pubkey.as_xmss().unwrap();
+        assert_eq!(xmss.params(), PARAMS);
+        assert_eq!(xmss.oid(), 1);
+        assert_eq!(xmss.raw_key().len(), 68);
+
+        assert_eq!(
+            pubkey.verify(b"data", b"sig").unwrap_err().kind(),
+            ErrorKind::UnsupportType
+        );
+        assert_eq!(
+            pubkey.serialize_pem().unwrap_err().kind(),
+            ErrorKind::UnsupportType
+        );
+        assert_eq!(PublicKey::from_blob(&blob).unwrap().blob().unwrap(), blob);
+    }
+}
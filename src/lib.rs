@@ -7,22 +7,31 @@
 //! - Public Key
 //!     - PEM
 //!     - OpenSSH
+//!     - RFC 4716 (read only)
+//! - Certificate
+//!     - OpenSSH user/host certificates
 //! - Private Key
 //!     - PEM
 //!     - OpenSSH v2
-//!     - PuTTY
+//!     - PuTTY v2/v3
 //!
 //! # Supported Key Type
 //! - DSA
 //! - RSA
 //! - EcDSA
+//! - EcDSA-SK (FIDO/U2F, the signing must be done on the token)
 //! - Ed25519
+//! - Ed25519-SK (FIDO/U2F, the signing must be done on the token)
+//! - XMSS (public keys only, no signature verification)
 //!
 //! ## Cargo Features
 //! - `openssl-cipher`: [default] Using OpenSSL as symmetric cipher
 //! - `rustcrypto-cipher`: Using RustCrypto as symmetric cipher
 //!     - Choicing this does not remove the `openssl` dependency, since many places still require to use OpenSSL.
 //! - `openssl-vendored`: Build with `openssl/vendored` feature
+//! - `unix-agent`: Enable the SSH agent client in the [`agent`](agent/index.html) module (unix only)
+//! - `weak-keys`: Enable checking the keys against the known-compromised key lists in the [`weakkeys`](weakkeys/index.html) module
+//! - `serde`: Implement `Serialize`/`Deserialize` for [`PublicKey`](keys/struct.PublicKey.html) as the OpenSSH format string
 //!
 //! # Example
 //! ```rust
@@ -51,6 +60,9 @@
 //! assert!(publickey.verify(SOME_DATA, &sign).unwrap());
 //! ```
 
+/// Client of the SSH agent
+#[cfg(all(unix, feature = "unix-agent"))]
+pub mod agent;
 /// Containing the encrypt/decrypt algorithm
 pub mod cipher;
 /// Containing the error type of this crate
@@ -59,8 +71,14 @@ pub mod error;
 pub mod format;
 /// Representing different types of public/private keys
 pub mod keys;
+/// Containing the message authentication code algorithms
+pub mod mac;
+mod ossl_info;
 /// Extension to read/write ssh data type representations defined in [RFC 4251](https://tools.ietf.org/html/rfc4251#section-5)
 pub mod sshbuf;
+/// Detect the known-compromised keys
+#[cfg(feature = "weak-keys")]
+pub mod weakkeys;
 
 pub use keys::Key;
 pub use keys::KeyPair;
@@ -68,3 +86,4 @@ pub use keys::KeyType;
 pub use keys::PrivateParts;
 pub use keys::PublicKey;
 pub use keys::PublicParts;
+pub use ossl_info::{openssl_info, OpensslInfo};
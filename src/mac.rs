@@ -0,0 +1,91 @@
+use self::internal_impl::*;
+use crate::error::OsshResult;
+
+/// Compute the HMAC-SHA1 of the data, as used by the hashed `known_hosts` entries
+pub fn hmac_sha1(key: &[u8], data: &[u8]) -> OsshResult<Vec<u8>> {
+    hmac_sha1_impl(key, data)
+}
+
+/// Compute the HMAC-SHA256 of the data, as used by the PuTTY key file version 3
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> OsshResult<Vec<u8>> {
+    hmac_sha256_impl(key, data)
+}
+
+#[cfg(feature = "rustcrypto-cipher")]
+mod internal_impl {
+    use crate::error::OsshResult;
+    use hmac::{Hmac, Mac};
+
+    fn hmac<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8]) -> OsshResult<Vec<u8>> {
+        let mut mac = <M as Mac>::new_from_slice(key)?;
+        mac.update(data);
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+
+    pub fn hmac_sha1_impl(key: &[u8], data: &[u8]) -> OsshResult<Vec<u8>> {
+        hmac::<Hmac<sha1::Sha1>>(key, data)
+    }
+
+    pub fn hmac_sha256_impl(key: &[u8], data: &[u8]) -> OsshResult<Vec<u8>> {
+        hmac::<Hmac<sha2::Sha256>>(key, data)
+    }
+}
+
+#[cfg(feature = "openssl-cipher")]
+mod internal_impl {
+    use crate::error::OsshResult;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::sign::Signer;
+
+    fn hmac(digest: MessageDigest, key: &[u8], data: &[u8]) -> OsshResult<Vec<u8>> {
+        // OpenSSL 3 rejects the empty key, but HMAC pads the key with zeros anyway
+        let key = if key.is_empty() { &[0u8][..] } else { key };
+        let pkey = PKey::hmac(key)?;
+        let mut signer = Signer::new(digest, &pkey)?;
+        signer.update(data)?;
+        Ok(signer.sign_to_vec()?)
+    }
+
+    pub fn hmac_sha1_impl(key: &[u8], data: &[u8]) -> OsshResult<Vec<u8>> {
+        hmac(MessageDigest::sha1(), key, data)
+    }
+
+    pub fn hmac_sha256_impl(key: &[u8], data: &[u8]) -> OsshResult<Vec<u8>> {
+        hmac(MessageDigest::sha256(), key, data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn hmac_test_vectors() {
+        // RFC 2202 test case 2
+        assert_eq!(
+            hmac_sha1(b"Jefe", b"what do ya want for nothing?").unwrap(),
+            hex!("effcdf6ae5eb2fa2d27416d5f184df9c259a7c79")
+        );
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?").unwrap(),
+            hex!("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+        // The empty key, as used by the unencrypted PuTTY key files version 3
+        assert_eq!(
+            hmac_sha256(b"", b"").unwrap(),
+            hex!("b613679a0814d9ec772f95d778c35fc5ff1697c493715653c6c712144292c5ad")
+        );
+        // RFC 4231 test case 6, the key is longer than the block size
+        assert_eq!(
+            hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )
+            .unwrap(),
+            hex!("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54")
+        );
+    }
+}
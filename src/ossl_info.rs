@@ -0,0 +1,58 @@
+use openssl::ec::EcGroup;
+use openssl::hash::{hash, MessageDigest};
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+
+// An Ed25519 private key of PKCS#8 with the zero seed, only used to probe the support
+const ED25519_PROBE_DER: [u8; 48] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// The version and the capabilities of the linked OpenSSL, returned by [`openssl_info()`](fn.openssl_info.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpensslInfo {
+    /// The version string, e.g. `OpenSSL 3.0.13 30 Jan 2024`
+    pub version: &'static str,
+    /// Whether OpenSSL supports the Ed25519 keys, which requires OpenSSL 1.1.1 or later
+    ///
+    /// The Ed25519 keys of this crate are handled by `ed25519-dalek`,
+    /// this only matters when converting them to or from the OpenSSL keys, e.g. PKCS#8.
+    pub has_ed25519: bool,
+    /// Whether OpenSSL supports the `secp256k1` curve
+    pub has_secp256k1: bool,
+    /// Whether the algorithms not approved by FIPS are blocked, e.g. in the FIPS mode
+    ///
+    /// This is detected by computing an MD5 digest, which is not allowed by FIPS.
+    pub fips: bool,
+}
+
+/// Query the version and the capabilities of the linked OpenSSL at runtime
+///
+/// The curves and the algorithms available depend on how OpenSSL is built and configured,
+/// so the tools can check them before constructing the keys.
+pub fn openssl_info() -> OpensslInfo {
+    OpensslInfo {
+        version: openssl::version::version(),
+        has_ed25519: PKey::private_key_from_der(&ED25519_PROBE_DER).is_ok(),
+        has_secp256k1: EcGroup::from_curve_name(Nid::SECP256K1).is_ok(),
+        fips: hash(MessageDigest::md5(), b"").is_err(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn openssl_info_probe() {
+        let info = openssl_info();
+        assert!(!info.version.is_empty());
+        // OpenSSL 1.1.1 and later
+        if openssl::version::number() >= 0x1010100f && !info.version.starts_with("LibreSSL") {
+            assert!(info.has_ed25519);
+        }
+        assert_eq!(info, openssl_info());
+    }
+}
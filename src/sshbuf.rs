@@ -2,6 +2,7 @@ use cryptovec::CryptoVec;
 pub use openssl::bn::{BigNum, BigNumRef};
 use std::io;
 use std::io::{Read, Result, Write};
+use std::ops::Deref;
 use std::str;
 use zeroize::{Zeroize, Zeroizing};
 
@@ -196,6 +197,44 @@ impl<R: io::Read + ?Sized> SshReadExt for R {
     */
 }
 
+/// A `BigNum` holding a secret value, which is cleared when dropped
+///
+/// OpenSSL frees a `BigNum` without clearing it, so the secret key parts are kept in this
+/// wrapper until they are handed over to the OpenSSL key, which clears them by itself.
+pub(crate) struct SecretBigNum(Option<BigNum>);
+
+impl SecretBigNum {
+    pub(crate) fn new(value: BigNum) -> Self {
+        SecretBigNum(Some(value))
+    }
+
+    /// Take the value out, which is no longer cleared by this wrapper
+    pub(crate) fn into_inner(mut self) -> BigNum {
+        self.0.take().expect("The value is only taken once")
+    }
+}
+
+impl Deref for SecretBigNum {
+    type Target = BigNumRef;
+
+    fn deref(&self) -> &BigNumRef {
+        self.0.as_ref().expect("The value is only taken once")
+    }
+}
+
+impl Drop for SecretBigNum {
+    fn drop(&mut self) {
+        if let Some(value) = self.0.as_mut() {
+            value.clear();
+        }
+    }
+}
+
+/// Read a multiple precision integer holding a secret value
+pub(crate) fn read_secret_mpint<R: Read + ?Sized>(reader: &mut R) -> io::Result<SecretBigNum> {
+    reader.read_mpint().map(SecretBigNum::new)
+}
+
 // --------------------------
 // ---- Helper Functions ----
 // --------------------------
@@ -310,6 +349,10 @@ impl<W: io::Write + ?Sized> SshWriteExt for W {
     fn write_mpint(&mut self, value: &BigNumRef) -> io::Result<()> {
         let mut buf = Zeroizing::new(vec![0x00u8]);
         let bnbuf = Zeroizing::new(value.to_vec());
+        // The value zero must be stored as an empty string
+        if bnbuf.is_empty() {
+            return self.write_string(&[]);
+        }
         buf.reserve(bnbuf.len());
         buf.extend(bnbuf.as_slice());
 
@@ -359,3 +402,52 @@ impl<W: io::Write + ?Sized> SshWriteExt for W {
     }
     */
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mpint_roundtrip(value: &[u8], expected: &[u8]) {
+        let bn = BigNum::from_slice(value).unwrap();
+        let mut buf = Vec::new();
+        buf.write_mpint(&bn).unwrap();
+        assert_eq!(buf.as_slice(), expected);
+        let decoded = io::Cursor::new(buf).read_mpint().unwrap();
+        assert_eq!(decoded, bn);
+    }
+
+    #[test]
+    fn secret_mpint() {
+        let mut buf = Vec::new();
+        buf.write_mpint(&BigNum::from_u32(0x1234).unwrap()).unwrap();
+        let mut reader = io::Cursor::new(buf);
+        let secret = read_secret_mpint(&mut reader).unwrap();
+        assert_eq!(*secret, *BigNum::from_u32(0x1234).unwrap());
+        assert_eq!(secret.into_inner(), BigNum::from_u32(0x1234).unwrap());
+        // Nothing is left to read
+        assert!(read_secret_mpint(&mut reader).is_err());
+    }
+
+    #[test]
+    fn mpint_zero() {
+        mpint_roundtrip(&[], &[0x00, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn mpint_multi_bytes() {
+        // Examples from RFC 4251 section 5
+        mpint_roundtrip(
+            &[0x09, 0xa3, 0x78, 0xf9, 0xb2, 0xe3, 0x32, 0xa7],
+            &[
+                0x00, 0x00, 0x00, 0x08, 0x09, 0xa3, 0x78, 0xf9, 0xb2, 0xe3, 0x32, 0xa7,
+            ],
+        );
+        mpint_roundtrip(&[0x80], &[0x00, 0x00, 0x00, 0x02, 0x00, 0x80]);
+        mpint_roundtrip(
+            &[0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01],
+            &[
+                0x00, 0x00, 0x00, 0x09, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            ],
+        );
+    }
+}
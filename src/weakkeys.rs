@@ -0,0 +1,142 @@
+use crate::error::*;
+use crate::keys::dsa::DSA_SHORT_NAME;
+use crate::keys::rsa::RSA_SHORT_NAME;
+use crate::keys::{FingerprintHash, PublicParts};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The directory where the `openssh-blacklist` packages of Debian and Ubuntu install the blocklists
+pub const DEBIAN_BLOCKLIST_DIR: &str = "/usr/share/ssh";
+
+// The blocklist stores the last 20 hex digits of the MD5 fingerprint
+const ENTRY_LEN: usize = 10;
+const MD5_LEN: usize = 16;
+
+/// A list of the known-compromised keys, e.g. the keys generated by the 2008 Debian OpenSSL bug
+///
+/// The list uses the format of the `openssh-blacklist` package (`/usr/share/ssh/blacklist.*`),
+/// where each line contains the hex MD5 fingerprint of a key without its first 12 digits.
+/// Empty lines and the lines starting with `#` are ignored.
+/// The full 32 digits fingerprints are also accepted.
+///
+/// The blocklists are not embedded in this crate, since they are too large.
+/// Load the lists for the key types and sizes you want to check,
+/// or use [`is_debian_weak_in()`](fn.is_debian_weak_in.html) to load the Debian ones from a directory.
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    entries: HashSet<[u8; ENTRY_LEN]>,
+}
+
+impl Blocklist {
+    /// Create an empty blocklist
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the entries of a blocklist file
+    pub fn extend_from_str(&mut self, list: &str) -> OsshResult<()> {
+        for line in list.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.entries.insert(parse_entry(line)?);
+        }
+        Ok(())
+    }
+
+    /// Add a key into the blocklist
+    pub fn insert<K: PublicParts + ?Sized>(&mut self, key: &K) -> OsshResult<()> {
+        self.entries.insert(key_entry(key)?);
+        Ok(())
+    }
+
+    /// Check if the key is in the blocklist
+    pub fn contains<K: PublicParts + ?Sized>(&self, key: &K) -> OsshResult<bool> {
+        Ok(self.entries.contains(&key_entry(key)?))
+    }
+
+    /// Return the number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return `true` if the blocklist has no entry
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl FromStr for Blocklist {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut list = Blocklist::new();
+        list.extend_from_str(s)?;
+        Ok(list)
+    }
+}
+
+/// Return the file name of the Debian blocklist for the key type and size, e.g. `blacklist.RSA-2048`
+///
+/// Only the RSA and DSA keys were affected by the Debian OpenSSL bug, `None` is returned for the other keys.
+pub fn debian_blocklist_name<K: PublicParts + ?Sized>(key: &K) -> Option<String> {
+    match key.short_keyname() {
+        keytype @ (RSA_SHORT_NAME | DSA_SHORT_NAME) => {
+            Some(format!("blacklist.{}-{}", keytype, key.size()))
+        }
+        _ => None,
+    }
+}
+
+/// Check if the key is one of the Debian weak keys, using the blocklists in the directory
+///
+/// The blocklist for the key type and size is loaded from
+/// [`debian_blocklist_name()`](fn.debian_blocklist_name.html) in `dir`.
+/// The keys which are not RSA or DSA are never weak.
+/// If the blocklist for the key can't be read, e.g. it is not installed or there is none for the key size,
+/// [`ErrorKind::IOError`](../error/enum.ErrorKind.html#variant.IOError) is returned,
+/// since the key can't be told to be safe.
+pub fn is_debian_weak_in<K: PublicParts + ?Sized, P: AsRef<Path>>(
+    key: &K,
+    dir: P,
+) -> OsshResult<bool> {
+    match debian_blocklist_name(key) {
+        Some(name) => {
+            let list: Blocklist = fs::read_to_string(dir.as_ref().join(name))?.parse()?;
+            list.contains(key)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Return the blocklist entry of the key, in the form stored in the `openssh-blacklist` files
+pub fn blocklist_fingerprint<K: PublicParts + ?Sized>(key: &K) -> OsshResult<String> {
+    Ok(key_entry(key)?
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+fn key_entry<K: PublicParts + ?Sized>(key: &K) -> OsshResult<[u8; ENTRY_LEN]> {
+    let fp = key.fingerprint(FingerprintHash::MD5)?;
+    Ok(fp[MD5_LEN - ENTRY_LEN..].try_into()?)
+}
+
+fn parse_entry(line: &str) -> OsshResult<[u8; ENTRY_LEN]> {
+    let digits = match line.len() {
+        l if l == ENTRY_LEN * 2 => line,
+        l if l == MD5_LEN * 2 => line
+            .get((MD5_LEN - ENTRY_LEN) * 2..)
+            .ok_or(ErrorKind::InvalidFormat)?,
+        _ => return Err(ErrorKind::InvalidFormat.into()),
+    };
+    let mut entry = [0u8; ENTRY_LEN];
+    for (i, b) in entry.iter_mut().enumerate() {
+        *b = digits
+            .get(i * 2..i * 2 + 2)
+            .and_then(|d| u8::from_str_radix(d, 16).ok())
+            .ok_or(ErrorKind::InvalidFormat)?;
+    }
+    Ok(entry)
+}
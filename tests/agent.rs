@@ -0,0 +1,135 @@
+#![cfg(all(unix, feature = "unix-agent"))]
+extern crate osshkeys;
+
+use osshkeys::agent::Client;
+use osshkeys::keys::*;
+use osshkeys::sshbuf::SshReadExt;
+use std::fs;
+use std::io::Cursor;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+
+mod utils;
+
+struct Agent {
+    process: Child,
+    dir: PathBuf,
+}
+
+impl Agent {
+    fn spawn(name: &str) -> Agent {
+        let dir = std::env::temp_dir().join(format!("osshkeys-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let process = Command::new("ssh-agent")
+            .arg("-D")
+            .arg("-a")
+            .arg(dir.join("agent.sock"))
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("ssh-agent is required to run this test");
+        for _ in 0..50 {
+            if dir.join("agent.sock").exists() {
+                break;
+            }
+            sleep(Duration::from_millis(100));
+        }
+        Agent { process, dir }
+    }
+
+    fn socket(&self) -> PathBuf {
+        self.dir.join("agent.sock")
+    }
+
+    fn add(&self, keyfile: &Path) {
+        let keypath = self.dir.join(keyfile.file_name().unwrap());
+        fs::copy(keyfile, &keypath).unwrap();
+        fs::set_permissions(&keypath, fs::Permissions::from_mode(0o600)).unwrap();
+        let status = Command::new("ssh-add")
+            .arg(&keypath)
+            .env("SSH_AUTH_SOCK", self.socket())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+}
+
+impl Drop for Agent {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn agent_request_identities() {
+    let agent = Agent::spawn("identities");
+    let keypath = utils::locate_crate_files("assets/openssh_ed25519");
+    let mut client = Client::connect(agent.socket()).unwrap();
+    assert!(client.request_identities().unwrap().is_empty());
+
+    agent.add(&keypath);
+    let keypair = KeyPair::from_keystr(fs::read_to_string(&keypath).unwrap(), None).unwrap();
+    let identities = client.request_identities().unwrap();
+    assert_eq!(identities.len(), 1);
+    assert_eq!(identities[0].0.blob().unwrap(), keypair.blob().unwrap());
+    assert_eq!(identities[0].1, keypair.comment());
+}
+
+#[test]
+fn agent_sign() {
+    let agent = Agent::spawn("sign");
+    agent.add(&utils::locate_crate_files("assets/openssh_ed25519"));
+    agent.add(&utils::locate_crate_files("assets/openssh_rsa"));
+    let mut client = Client::connect(agent.socket()).unwrap();
+
+    for (mut pubkey, _) in client.request_identities().unwrap() {
+        if pubkey.keytype() == KeyType::RSA {
+            // Request the rsa-sha2-256 signature
+            pubkey = PublicKey::from_keystr(&pubkey.serialize().unwrap().replacen(
+                "ssh-rsa",
+                "rsa-sha2-256",
+                1,
+            ))
+            .unwrap();
+        }
+
+        let sigblob = client.sign(&pubkey, b"data").unwrap();
+        let mut reader = Cursor::new(sigblob);
+        assert_eq!(reader.read_utf8().unwrap(), pubkey.keyname());
+        let sig = reader.read_string().unwrap();
+        assert!(pubkey.verify(b"data", &sig).unwrap());
+    }
+}
+
+#[test]
+fn agent_signer_matches_local() {
+    use osshkeys::agent::Signer;
+
+    let agent = Agent::spawn("signer");
+    for name in ["assets/openssh_ed25519", "assets/openssh_rsa"] {
+        let keypath = utils::locate_crate_files(name);
+        agent.add(&keypath);
+        let keypair = KeyPair::from_keystr(fs::read_to_string(&keypath).unwrap(), None).unwrap();
+        let pubkey = keypair.clone_public_key().unwrap();
+
+        let mut local = Signer::Local(keypair);
+        let mut remote = Signer::Agent(Client::connect(agent.socket()).unwrap(), pubkey);
+        assert_eq!(
+            local.public_key().unwrap().blob().unwrap(),
+            remote.public_key().unwrap().blob().unwrap()
+        );
+        // Both RSA PKCS#1 v1.5 and Ed25519 signatures are deterministic
+        for alg in [None, Some(rsa::RsaSignature::SHA2_256)] {
+            assert_eq!(
+                local.sign(b"data", alg).unwrap(),
+                remote.sign(b"data", alg).unwrap()
+            );
+        }
+    }
+}
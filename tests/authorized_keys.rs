@@ -0,0 +1,238 @@
+extern crate osshkeys;
+
+use osshkeys::format::{
+    canonicalize_authorized_keys, lint_authorized_keys, AuthKeysIssue, AuthKeysIssueKind,
+};
+use osshkeys::keys::*;
+use std::fs;
+
+mod utils;
+
+// The key line without the comment
+fn load_key(name: &str) -> String {
+    let keystr = fs::read_to_string(utils::locate_crate_files(name)).unwrap();
+    let mut fields = keystr.split_whitespace();
+    format!("{} {}", fields.next().unwrap(), fields.next().unwrap())
+}
+
+fn issue(line: usize, kind: AuthKeysIssueKind) -> AuthKeysIssue {
+    AuthKeysIssue { line, kind }
+}
+
+#[test]
+fn lint_authorized_keys_clean() {
+    let input = format!(
+        "# admins\n\n{} alice@example.com\nrestrict,command=\"echo \\\"hi, there\\\"\" {} backup key\n",
+        load_key("assets/openssh_ed25519.pub"),
+        load_key("assets/openssh_rsa.pub"),
+    );
+    assert!(lint_authorized_keys(&input).is_empty());
+    assert_eq!(canonicalize_authorized_keys(&input), input);
+}
+
+#[test]
+fn lint_authorized_keys_issues() {
+    let ed25519 = load_key("assets/openssh_ed25519.pub");
+    let ecdsa = load_key("assets/openssh_ecdsa.pub");
+    let dsa = load_key("assets/openssh_dsa.pub");
+    let rsa1024 = KeyPair::generate(KeyType::RSA, 1024)
+        .unwrap()
+        .serialize_publickey()
+        .unwrap();
+    let input = [
+        ed25519.clone(),
+        format!("no-pty {}", ed25519),
+        dsa,
+        rsa1024,
+        format!("from=\"10.0.0.0/8,*\",no-such-option {}", ecdsa),
+        "ssh-ed25519 AAAAnotakey".to_owned(),
+        format!("command=\"unterminated {}", ecdsa),
+        format!(" {}\t comment", ecdsa),
+    ]
+    .join("\n");
+
+    assert_eq!(
+        lint_authorized_keys(&input),
+        vec![
+            issue(2, AuthKeysIssueKind::Duplicate(1)),
+            issue(3, AuthKeysIssueKind::DeprecatedAlgorithm),
+            issue(4, AuthKeysIssueKind::Weak),
+            issue(
+                5,
+                AuthKeysIssueKind::SuspiciousOption("from=\"10.0.0.0/8,*\"".into())
+            ),
+            issue(
+                5,
+                AuthKeysIssueKind::SuspiciousOption("no-such-option".into())
+            ),
+            issue(6, AuthKeysIssueKind::Unparseable),
+            issue(7, AuthKeysIssueKind::Unparseable),
+            issue(8, AuthKeysIssueKind::Duplicate(5)),
+            issue(8, AuthKeysIssueKind::Whitespace),
+        ]
+    );
+}
+
+#[test]
+fn canonicalize_authorized_keys_whitespace() {
+    let key = load_key("assets/openssh_ed25519.pub");
+    let (keytype, keyb64) = key.split_once(' ').unwrap();
+    let input = format!(
+        "  no-pty  {}\t{}   my  key  \n#  kept as is\nnot a key\n",
+        keytype, keyb64
+    );
+    assert_eq!(
+        canonicalize_authorized_keys(&input),
+        format!("no-pty {} my  key\n#  kept as is\nnot a key\n", key)
+    );
+}
+
+#[test]
+fn parse_authorized_keys_entries() {
+    use osshkeys::error::ErrorKind;
+
+    let ed25519 = load_key("assets/openssh_ed25519.pub");
+    let rsa = load_key("assets/openssh_rsa.pub");
+    let input = format!(
+        "# admins\n\n{}  alice@example.com\r\nno-pty,command=\"echo \\\"a b\\\"\" {}\nssh-ed25519 AAAAbroken bob\ngarbage\n\t{}\n",
+        ed25519, rsa, ed25519,
+    );
+    let entries = parse_authorized_keys(input.as_bytes()).unwrap();
+    let lines: Vec<_> = entries.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, [3, 4, 5, 6, 7]);
+
+    let alice = entries[0].1.as_ref().ok().unwrap();
+    assert_eq!(alice.options, None);
+    assert_eq!(alice.comment.as_deref(), Some("alice@example.com"));
+    assert_eq!(alice.key.comment(), "alice@example.com");
+    assert_eq!(
+        alice
+            .key
+            .fingerprint_string(FingerprintHash::SHA256)
+            .unwrap(),
+        "SHA256:4u4w9KoU+yfqclRMG+SH/AmFt80Nx+8pMXVrWYw2zT0"
+    );
+
+    let restricted = entries[1].1.as_ref().ok().unwrap();
+    assert_eq!(
+        restricted.options.as_deref(),
+        Some("no-pty,command=\"echo \\\"a b\\\"\"")
+    );
+    assert_eq!(restricted.key.keytype(), KeyType::RSA);
+    assert_eq!(restricted.comment, None);
+
+    // The invalid key is reported, instead of being taken as the options
+    assert_eq!(
+        entries[2].1.as_ref().err().unwrap().kind(),
+        ErrorKind::Base64Error
+    );
+    assert_eq!(
+        entries[3].1.as_ref().err().unwrap().kind(),
+        ErrorKind::InvalidKeyFormat
+    );
+    assert!(entries[4].1.is_ok());
+
+    // The non-UTF-8 line doesn't stop the parse
+    let mut input = b"\xff\n".to_vec();
+    input.extend_from_slice(ed25519.as_bytes());
+    let entries = parse_authorized_keys(input.as_slice()).unwrap();
+    assert!(entries[0].1.is_err());
+    assert_eq!(entries[1].0, 2);
+    assert!(entries[1].1.is_ok());
+}
+
+#[test]
+fn authorized_key_to_string() {
+    use osshkeys::error::ErrorKind;
+
+    let ed25519 = load_key("assets/openssh_ed25519.pub");
+    let rsa = load_key("assets/openssh_rsa.pub");
+    let dsa = load_key("assets/openssh_dsa.pub");
+    let input = format!(
+        "# admins\n{} alice@example.com\nno-pty,command=\"echo \\\"a, b\\\"\"  {}\n\n  from=\"10.0.0.1\"\t{}   backup  key\nssh-rsa {}\n",
+        ed25519,
+        rsa,
+        dsa,
+        rsa.split_whitespace().nth(1).unwrap(),
+    );
+    let output: String = parse_authorized_keys(input.as_bytes())
+        .unwrap()
+        .into_iter()
+        .map(|(_, entry)| entry.ok().unwrap().to_string() + "\n")
+        .collect();
+    let canonical: String = canonicalize_authorized_keys(&input)
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_owned() + "\n")
+        .collect();
+    assert_eq!(output, canonical);
+    // Stable after round-tripping
+    let reparsed: String = parse_authorized_keys(output.as_bytes())
+        .unwrap()
+        .into_iter()
+        .map(|(_, entry)| entry.ok().unwrap().to_string() + "\n")
+        .collect();
+    assert_eq!(reparsed, output);
+
+    let mut entry = parse_authorized_keys(format!("{} alice\n", ed25519).as_bytes())
+        .unwrap()
+        .remove(0)
+        .1
+        .ok()
+        .unwrap();
+    entry.add_option("restrict", None).unwrap();
+    entry
+        .add_option("from", Some("10.0.0.0/8,192.168.0.0/16"))
+        .unwrap();
+    entry.add_option("command", Some("echo \"hi\"")).unwrap();
+    entry.add_option("environment", Some("NAME=value")).unwrap();
+    assert_eq!(
+        entry.to_string(),
+        format!(
+            "restrict,from=\"10.0.0.0/8,192.168.0.0/16\",command=\"echo \\\"hi\\\"\",environment=\"NAME=value\" {} alice",
+            ed25519
+        )
+    );
+    assert!(lint_authorized_keys(&entry.to_string()).is_empty());
+
+    entry.comment = Some("alice\nssh-ed25519 AAAA".into());
+    assert_eq!(entry.to_string().lines().count(), 1);
+
+    // sshd only unescapes `\"`, so the closing quote after a trailing backslash would be escaped
+    assert_eq!(
+        entry
+            .add_option("command", Some("dir C:\\"))
+            .err()
+            .unwrap()
+            .kind(),
+        ErrorKind::InvalidArgument
+    );
+}
+
+#[test]
+fn authorized_keys_option_backslash_roundtrip() {
+    let ed25519 = load_key("assets/openssh_ed25519.pub");
+    let mut entry = parse_authorized_keys(format!("{}\n", ed25519).as_bytes())
+        .unwrap()
+        .remove(0)
+        .1
+        .ok()
+        .unwrap();
+    entry.add_option("command", Some("echo \\\",done")).unwrap();
+    entry.add_option("no-pty", None).unwrap();
+    let line = entry.to_string();
+    assert_eq!(
+        line,
+        format!("command=\"echo \\\\\",done\",no-pty {}", ed25519)
+    );
+
+    // The backslash before the escaped quote is literal, so the comma is still inside the quotes
+    let reparsed = parse_authorized_keys(format!("{}\n", line).as_bytes())
+        .unwrap()
+        .remove(0)
+        .1
+        .ok()
+        .unwrap();
+    assert_eq!(reparsed.options, entry.options);
+    assert!(lint_authorized_keys(&line).is_empty());
+}
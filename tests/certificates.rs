@@ -0,0 +1,260 @@
+extern crate osshkeys;
+
+use base64::prelude::*;
+use osshkeys::error::ErrorKind;
+use osshkeys::keys::cert::*;
+use osshkeys::keys::*;
+use std::fs;
+
+mod utils;
+
+fn read_asset(name: &str) -> String {
+    fs::read_to_string(utils::locate_crate_files(name)).unwrap()
+}
+
+#[test]
+fn cert_parse_user() {
+    let cert = Certificate::from_keystring(&read_asset("assets/openssh_ed25519-cert.pub")).unwrap();
+    assert_eq!(cert.keyname(), "ssh-ed25519-cert-v01@openssh.com");
+    assert_eq!(cert.cert_type(), CertType::User);
+    assert!(!cert.is_host_cert());
+    assert_eq!(cert.serial(), 42);
+    assert_eq!(cert.key_id(), "osshkeys-user@example.com");
+    assert_eq!(cert.valid_principals(), ["alice", "bob"]);
+    assert_eq!(cert.valid_after(), 1577836800);
+    assert_eq!(cert.valid_before(), 2145916800);
+    assert_eq!(cert.nonce().len(), 32);
+    assert_eq!(cert.comment(), "osshkeys_user-cert");
+
+    let options = cert.critical_options();
+    assert_eq!(options.len(), 2);
+    assert_eq!(options["force-command"], "/usr/bin/true");
+    assert_eq!(options["source-address"], "10.0.0.0/8,192.168.1.1");
+    let extensions: Vec<_> = cert.extensions().keys().map(String::as_str).collect();
+    assert_eq!(
+        extensions,
+        [
+            "permit-X11-forwarding",
+            "permit-agent-forwarding",
+            "permit-pty",
+            "permit-user-rc"
+        ]
+    );
+    assert!(cert.extensions().values().all(String::is_empty));
+
+    // The certified key is the plain public key
+    let pubkey = PublicKey::from_keystr(&read_asset("assets/openssh_ed25519.pub")).unwrap();
+    assert_eq!(cert.public_key().blob().unwrap(), pubkey.blob().unwrap());
+    let ca = PublicKey::from_keystr(&read_asset("assets/cert_ca_ed25519.pub")).unwrap();
+    assert_eq!(cert.signature_key().blob().unwrap(), ca.blob().unwrap());
+    assert!(cert.verify_signature().unwrap());
+}
+
+#[test]
+fn cert_matches_keypair() {
+    let cert = Certificate::from_keystring(&read_asset("assets/openssh_ed25519-cert.pub")).unwrap();
+    let key = KeyPair::from_keystr(read_asset("assets/openssh_ed25519"), None).unwrap();
+    assert!(key.matches_certificate(&cert));
+
+    let other = KeyPair::from_keystr(read_asset("assets/cert_ca_ed25519"), None).unwrap();
+    assert!(!other.matches_certificate(&cert));
+    let ecdsa = KeyPair::from_keystr(read_asset("assets/openssh_ecdsa"), None).unwrap();
+    assert!(!ecdsa.matches_certificate(&cert));
+}
+
+#[test]
+fn cert_valid_with_skew() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let cert = Certificate::from_keystring(&read_asset("assets/openssh_ed25519-cert.pub")).unwrap();
+    let at = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+    let skew = Duration::from_secs(300);
+    let (after, before) = (cert.valid_after(), cert.valid_before());
+
+    assert!(cert.is_valid_at_with_skew(at(after), Duration::ZERO));
+    assert!(!cert.is_valid_at_with_skew(at(after - 1), Duration::ZERO));
+    assert!(cert.is_valid_at_with_skew(at(before - 1), Duration::ZERO));
+    assert!(!cert.is_valid_at_with_skew(at(before), Duration::ZERO));
+
+    // valid_after is included and valid_before is excluded, after widening by the skew
+    assert!(cert.is_valid_at_with_skew(at(after - 300), skew));
+    assert!(!cert.is_valid_at_with_skew(at(after - 301), skew));
+    assert!(cert.is_valid_at_with_skew(at(before + 299), skew));
+    assert!(!cert.is_valid_at_with_skew(at(before + 300), skew));
+
+    // The skew doesn't overflow
+    assert!(cert.is_valid_at_with_skew(at(after), Duration::from_secs(u64::MAX)));
+    assert!(!cert.is_valid_at_with_skew(UNIX_EPOCH, Duration::ZERO));
+}
+
+#[test]
+fn cert_parse_host() {
+    let cert = Certificate::from_keystring(&read_asset("assets/openssh_ecdsa-cert.pub")).unwrap();
+    assert_eq!(cert.keyname(), "ecdsa-sha2-nistp256-cert-v01@openssh.com");
+    assert_eq!(cert.cert_type(), CertType::Host);
+    assert!(cert.is_host_cert());
+    assert_eq!(cert.serial(), 7);
+    assert_eq!(cert.valid_principals(), ["host.example.com", "10.1.2.3"]);
+    assert!(cert.critical_options().is_empty());
+    assert!(cert.extensions().is_empty());
+    assert_eq!(cert.public_key().keytype(), KeyType::ECDSA);
+
+    // Signed by the RSA CA with `rsa-sha2-512`
+    let ca = PublicKey::from_keystr(&read_asset("assets/openssh_rsa.pub")).unwrap();
+    assert_eq!(cert.signature_key().blob().unwrap(), ca.blob().unwrap());
+    assert!(cert.verify_signature().unwrap());
+}
+
+#[test]
+fn cert_tampered() {
+    let keystr = read_asset("assets/openssh_ed25519-cert.pub");
+    let mut blob = BASE64_STANDARD
+        .decode(keystr.split_whitespace().nth(1).unwrap())
+        .unwrap();
+    let cert = Certificate::from_blob(&blob).unwrap();
+    assert_eq!(cert.blob(), blob.as_slice());
+    assert_eq!(cert.comment(), "");
+
+    // Change the serial, which is right after the name, the nonce and the public key
+    let serial_pos = (4 + 32) + (4 + 32) + (4 + 32);
+    assert_eq!(blob[serial_pos + 7], 42);
+    blob[serial_pos + 7] = 43;
+    let cert = Certificate::from_blob(&blob).unwrap();
+    assert_eq!(cert.serial(), 43);
+    assert!(!cert.verify_signature().unwrap());
+
+    // Trailing data after the signature
+    blob.push(0);
+    let err = Certificate::from_blob(&blob).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidKeyFormat);
+}
+
+#[test]
+fn cert_invalid_keystring() {
+    let keystr = read_asset("assets/openssh_ed25519-cert.pub");
+    let renamed = keystr.replacen("ssh-ed25519-cert", "ssh-rsa-cert", 1);
+    let err = Certificate::from_keystring(&renamed).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::TypeNotMatch);
+
+    // A plain public key is not a certificate
+    let err = Certificate::from_keystring(&read_asset("assets/openssh_ed25519.pub"))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::UnsupportType);
+    let err = Certificate::from_keystring("ssh-ed25519-cert-v01@openssh.com")
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidKeyFormat);
+}
+
+#[test]
+fn cert_sign_user() {
+    let ca = KeyPair::from_keystr(read_asset("assets/cert_ca_ed25519"), None).unwrap();
+    let subject = PublicKey::from_keystr(&read_asset("assets/openssh_ed25519.pub")).unwrap();
+    let mut opts = CertOptions {
+        serial: 42,
+        key_id: "osshkeys-user@example.com".to_owned(),
+        valid_principals: vec!["alice".to_owned(), "bob".to_owned()],
+        valid_after: 1577836800,
+        valid_before: 2145916800,
+        ..Default::default()
+    };
+    opts.critical_options
+        .insert("force-command".to_owned(), "/usr/bin/true".to_owned());
+    opts.extensions
+        .insert("permit-pty".to_owned(), String::new());
+    let cert = ca.sign_certificate(&subject, opts).unwrap();
+    assert_eq!(cert.keyname(), "ssh-ed25519-cert-v01@openssh.com");
+    assert_eq!(cert.cert_type(), CertType::User);
+    assert_eq!(cert.serial(), 42);
+    assert_eq!(cert.valid_principals(), ["alice", "bob"]);
+    assert_eq!(cert.critical_options()["force-command"], "/usr/bin/true");
+    assert_eq!(cert.extensions()["permit-pty"], "");
+    assert_eq!(cert.public_key().blob().unwrap(), subject.blob().unwrap());
+    assert_eq!(cert.signature_key().blob().unwrap(), ca.blob().unwrap());
+    assert_eq!(cert.comment(), subject.comment());
+    assert!(cert.verify_signature().unwrap());
+
+    // Round trip through the `-cert.pub` file format
+    let keystr = cert.serialize_certificate();
+    assert!(keystr.starts_with("ssh-ed25519-cert-v01@openssh.com AAAA"));
+    let parsed = Certificate::from_keystring(&keystr).unwrap();
+    assert_eq!(parsed.blob(), cert.blob());
+    assert_eq!(parsed.comment(), cert.comment());
+
+    // The nonce is random
+    let other = ca
+        .sign_certificate(&subject, CertOptions::default())
+        .unwrap();
+    assert_ne!(other.nonce(), cert.nonce());
+}
+
+#[test]
+fn cert_sign_host_rsa_ca() {
+    let ca = KeyPair::from_keystr(read_asset("assets/openssh_rsa"), None).unwrap();
+    let subject = PublicKey::from_keystr(&read_asset("assets/openssh_ecdsa.pub")).unwrap();
+    let opts = CertOptions {
+        serial: 7,
+        cert_type: CertType::Host,
+        valid_principals: vec!["host.example.com".to_owned()],
+        ..Default::default()
+    };
+    let cert = ca.sign_certificate(&subject, opts).unwrap();
+    assert_eq!(cert.keyname(), "ecdsa-sha2-nistp256-cert-v01@openssh.com");
+    assert_eq!(cert.cert_type(), CertType::Host);
+    assert_eq!(cert.valid_after(), 0);
+    assert_eq!(cert.valid_before(), u64::MAX);
+    // Valid forever, also with the clock skew
+    let skew = std::time::Duration::from_secs(300);
+    assert!(cert.is_valid_at_with_skew(std::time::SystemTime::now(), skew));
+    assert!(cert.is_valid_at_with_skew(std::time::UNIX_EPOCH, skew));
+    assert!(cert.verify_signature().unwrap());
+    // Signed with `rsa-sha2-512`
+    assert!(cert.signature().starts_with(b"\0\0\0\x0crsa-sha2-512"));
+}
+
+#[test]
+fn cert_reject_rsa_sha1_ca() {
+    let ca = KeyPair::from_keystr(read_asset("assets/openssh_rsa"), None).unwrap();
+    let subject = PublicKey::from_keystr(&read_asset("assets/openssh_ed25519.pub")).unwrap();
+    let cert = ca
+        .sign_certificate(&subject, CertOptions::default())
+        .unwrap();
+    // Sign the same certificate again with `ssh-rsa`
+    let signed = &cert.blob()[..cert.blob().len() - 4 - cert.signature().len()];
+    let signature = ca
+        .sign_with_alg(signed, Some(rsa::RsaSignature::SHA1))
+        .unwrap();
+    let mut blob = signed.to_vec();
+    blob.extend_from_slice(&(signature.len() as u32).to_be_bytes());
+    blob.extend_from_slice(&signature);
+    let cert = Certificate::from_blob(&blob).unwrap();
+    assert!(cert.signature().starts_with(b"\0\0\0\x07ssh-rsa"));
+    assert_eq!(
+        cert.verify_signature().unwrap_err().kind(),
+        ErrorKind::WeakSignature
+    );
+    assert_eq!(
+        cert.verify_signature_with_policy(VerifyPolicy::strict())
+            .unwrap_err()
+            .kind(),
+        ErrorKind::WeakSignature
+    );
+    // Accepted when opted in
+    assert!(cert
+        .verify_signature_with_policy(VerifyPolicy::default())
+        .unwrap());
+}
+
+#[test]
+fn cert_sign_invalid_validity() {
+    let ca = KeyPair::from_keystr(read_asset("assets/cert_ca_ed25519"), None).unwrap();
+    let subject = PublicKey::from_keystr(&read_asset("assets/openssh_ed25519.pub")).unwrap();
+    let opts = CertOptions {
+        valid_after: 2,
+        valid_before: 1,
+        ..Default::default()
+    };
+    let err = ca.sign_certificate(&subject, opts).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+}
@@ -0,0 +1,103 @@
+extern crate osshkeys;
+
+use osshkeys::cipher::Cipher;
+use osshkeys::error::ErrorKind;
+use osshkeys::format::{diff_keys, KeyFormat};
+use osshkeys::KeyPair;
+use std::fs;
+
+mod utils;
+
+fn read_asset(name: &str) -> String {
+    fs::read_to_string(utils::locate_crate_files(name)).unwrap()
+}
+
+#[test]
+fn diff_same_key_different_format() {
+    let diff = diff_keys(
+        &read_asset("assets/openssh_rsa"),
+        &read_asset("assets/pem_rsa"),
+    )
+    .unwrap();
+    assert!(diff.same_key);
+    assert_eq!(diff.a.format, KeyFormat::OpenSshPrivate);
+    assert_eq!(diff.b.format, KeyFormat::PemPrivate);
+    assert_eq!(diff.a.comment.as_deref(), Some("osshkeys_rsa-test"));
+    assert_eq!(diff.b.comment, None);
+    assert!(diff.format_differs());
+    assert!(!diff.comment_differs());
+    assert!(!diff.encryption_differs());
+
+    let diff = diff_keys(
+        &read_asset("assets/openssh_rsa.pub"),
+        &read_asset("assets/rfc4716_rsa.pub"),
+    )
+    .unwrap();
+    assert!(diff.same_key);
+    assert_eq!(diff.a.format, KeyFormat::OpenSshPublic);
+    assert_eq!(diff.b.format, KeyFormat::Rfc4716Public);
+    assert!(diff.comment_differs());
+
+    let diff = diff_keys(
+        &read_asset("assets/openssh_rsa"),
+        &read_asset("assets/openssh_rsa_enc"),
+    )
+    .unwrap();
+    assert!(!diff.same_key);
+    assert!(diff.b.encrypted);
+
+    // The encrypted OpenSSH private key is compared by its plain text public key
+    let diff = diff_keys(
+        &read_asset("assets/openssh_rsa_enc.pub.pem"),
+        &read_asset("assets/openssh_rsa_enc"),
+    )
+    .unwrap();
+    assert!(diff.same_key);
+    assert_eq!(diff.a.format, KeyFormat::PemPublic);
+}
+
+#[test]
+fn diff_reencrypted_key() {
+    let keystr = read_asset("assets/openssh_ed25519");
+    let key = KeyPair::from_keystr(&keystr, None).unwrap();
+    let enc1 = key
+        .serialize_openssh(Some("12345678"), Cipher::Aes256_Ctr)
+        .unwrap();
+    let enc2 = key
+        .serialize_openssh(Some("12345678"), Cipher::Aes256_Ctr)
+        .unwrap();
+    assert_ne!(enc1, enc2);
+
+    // Only the salt changed
+    let diff = diff_keys(&enc1, &enc2).unwrap();
+    assert!(diff.is_identical());
+
+    let diff = diff_keys(&keystr, &enc1).unwrap();
+    assert!(diff.same_key);
+    assert!(diff.encryption_differs());
+    assert!(!diff.format_differs());
+    // The encrypted comment can't be compared
+    assert_eq!(diff.b.comment, None);
+    assert!(!diff.comment_differs());
+    assert!(!diff.is_identical());
+}
+
+#[test]
+fn diff_encrypted_pem() {
+    for name in ["assets/pem_rsa_enc", "assets/pkcs8_rsa_enc"] {
+        assert_eq!(
+            diff_keys(&read_asset(name), &read_asset("assets/openssh_rsa"))
+                .unwrap_err()
+                .kind(),
+            ErrorKind::PassphraseRequired
+        );
+        // The same as parsing without the passphrase
+        assert_eq!(
+            KeyPair::from_keystr(read_asset(name), None)
+                .err()
+                .unwrap()
+                .kind(),
+            ErrorKind::PassphraseRequired
+        );
+    }
+}
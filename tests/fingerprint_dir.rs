@@ -0,0 +1,167 @@
+extern crate osshkeys;
+
+use base64::prelude::*;
+use osshkeys::format::fingerprint_dir;
+use osshkeys::keys::*;
+use sha2::{Digest, Sha256};
+use std::fs;
+
+mod utils;
+
+#[test]
+fn fingerprint_string_format() {
+    let pubdata =
+        fs::read_to_string(utils::locate_crate_files("assets/openssh_ed25519_enc.pub")).unwrap();
+    let pubkey = PublicKey::from_keystr(&pubdata).unwrap();
+    assert_eq!(
+        pubkey.fingerprint_string(FingerprintHash::MD5).unwrap(),
+        "MD5:d2:95:52:b0:c8:7d:7f:f1:ac:b3:c2:22:9e:78:33:21"
+    );
+    assert!(pubkey
+        .fingerprint_string(FingerprintHash::SHA256)
+        .unwrap()
+        .starts_with("SHA256:"));
+}
+
+#[test]
+fn fingerprint_input_matches_hash() {
+    for name in [
+        "assets/openssh_rsa.pub",
+        "assets/openssh_ecdsa.pub",
+        "assets/pem_dsa.pub",
+    ] {
+        let pubdata = fs::read_to_string(utils::locate_crate_files(name)).unwrap();
+        let pubkey = PublicKey::from_keystr(&pubdata).unwrap();
+        let input = pubkey.fingerprint_input().unwrap();
+        // The hashed bytes are the base64 decoded field in the public key file
+        let field = pubdata.split_ascii_whitespace().nth(1).unwrap();
+        assert_eq!(input, BASE64_STANDARD.decode(field).unwrap());
+        assert_eq!(
+            Sha256::digest(&input).as_slice(),
+            pubkey
+                .fingerprint(FingerprintHash::SHA256)
+                .unwrap()
+                .as_slice()
+        );
+    }
+}
+
+#[test]
+fn fingerprint_hash_introspect() {
+    let pubdata =
+        fs::read_to_string(utils::locate_crate_files("assets/openssh_ed25519.pub")).unwrap();
+    let pubkey = PublicKey::from_keystr(&pubdata).unwrap();
+
+    let names: Vec<&str> = FingerprintHash::all().iter().map(|h| h.name()).collect();
+    assert_eq!(names, ["MD5", "SHA1", "SHA256", "SHA512"]);
+    for hash in FingerprintHash::all() {
+        assert_eq!(pubkey.fingerprint(*hash).unwrap().len(), hash.digest_len());
+        assert!(pubkey
+            .fingerprint_string(*hash)
+            .unwrap()
+            .starts_with(&format!("{}:", hash.name())));
+    }
+}
+
+#[test]
+fn fingerprint_raw_blob() {
+    for name in [
+        "assets/openssh_rsa.pub",
+        "assets/openssh_ecdsa.pub",
+        "assets/openssh_ed25519.pub",
+        "assets/pem_dsa.pub",
+    ] {
+        let pubdata = fs::read_to_string(utils::locate_crate_files(name)).unwrap();
+        let field = pubdata.split_ascii_whitespace().nth(1).unwrap();
+        let blob = BASE64_STANDARD.decode(field).unwrap();
+        for hash in FingerprintHash::all() {
+            assert_eq!(
+                fingerprint_blob(&blob, *hash).unwrap(),
+                PublicKey::from_blob(&blob)
+                    .unwrap()
+                    .fingerprint(*hash)
+                    .unwrap()
+            );
+        }
+    }
+    assert!(fingerprint_blob(&[0, 0, 0, 8, b'x'], FingerprintHash::SHA256).is_err());
+}
+
+#[test]
+fn fingerprint_randomart_header() {
+    let keypair = KeyPair::from_keystr(
+        fs::read_to_string(utils::locate_crate_files("assets/openssh_ed25519")).unwrap(),
+        None,
+    )
+    .unwrap();
+    let randomart = fs::read_to_string(utils::locate_crate_files(
+        "assets/openssh_ed25519.randomart",
+    ))
+    .unwrap();
+    let pubkey = keypair.clone_public_key().unwrap();
+    assert_eq!(
+        pubkey.randomart(FingerprintHash::SHA256).unwrap(),
+        randomart
+    );
+    assert_eq!(
+        keypair.randomart(FingerprintHash::SHA256).unwrap(),
+        randomart
+    );
+
+    for (keytype, bits, header) in [
+        (KeyType::RSA, 3072, "+---[RSA 3072]----+"),
+        (KeyType::ECDSA, 384, "+---[ECDSA 384]---+"),
+        (KeyType::ED25519, 256, "+--[ED25519 256]--+"),
+    ] {
+        let keypair = KeyPair::generate(keytype, bits).unwrap();
+        let art = keypair.randomart(FingerprintHash::SHA512).unwrap();
+        let lines: Vec<&str> = art.lines().collect();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], header);
+        assert_eq!(lines[10], "+----[SHA512]-----+");
+        assert!(lines[1..10].iter().all(|l| l.len() == 19));
+    }
+}
+
+#[test]
+fn fingerprint_into_reuses_buffer() {
+    let mut out = Vec::with_capacity(64);
+    for name in ["assets/openssh_rsa.pub", "assets/openssh_ed25519.pub"] {
+        let pubdata = fs::read_to_string(utils::locate_crate_files(name)).unwrap();
+        let pubkey = PublicKey::from_keystr(&pubdata).unwrap();
+        for hash in FingerprintHash::all() {
+            pubkey.fingerprint_into(*hash, &mut out).unwrap();
+            assert_eq!(out, pubkey.fingerprint(*hash).unwrap());
+        }
+    }
+    assert_eq!(out.capacity(), 64);
+}
+
+#[test]
+fn fingerprint_assets_dir() {
+    let assets = utils::locate_crate_files("assets");
+    let result = fingerprint_dir(&assets, FingerprintHash::SHA256);
+
+    // Sorted by path
+    assert!(result.windows(2).all(|w| w[0].0 <= w[1].0));
+    // Randomart files are not keys
+    assert!(result
+        .iter()
+        .all(|(p, _)| p.extension().map_or(true, |ext| ext != "randomart")));
+
+    let lookup = |name: &str| {
+        let path = assets.join(name);
+        result
+            .iter()
+            .find(|(p, _)| p == &path)
+            .map(|(_, fp)| fp.as_ref().map(String::as_str).map_err(|e| e.kind()))
+    };
+    let pubfp = lookup("openssh_ed25519.pub").unwrap().unwrap();
+    assert_eq!(lookup("openssh_ed25519").unwrap(), Ok(pubfp));
+    assert_eq!(
+        lookup("openssh_rsa_enc.pub.pem").unwrap(),
+        lookup("openssh_rsa_enc.pub").unwrap()
+    );
+    // Encrypted private keys cannot be read without the passphrase
+    assert!(lookup("openssh_ed25519_enc").unwrap().is_err());
+}
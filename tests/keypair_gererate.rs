@@ -7,7 +7,7 @@ fn rsa_generate() {
     let key = KeyPair::generate(KeyType::RSA, 0).unwrap();
     println!("{}", key.clone_public_key().unwrap());
     assert_eq!(key.keytype(), KeyType::RSA);
-    assert_eq!(key.size(), 2048);
+    assert_eq!(key.size(), 3072);
     assert_eq!(key.keyname(), "rsa-sha2-512");
 }
 
@@ -32,6 +32,54 @@ fn rsa_generate_strange() {
     assert_eq!(key.keyname(), "rsa-sha2-512");
 }
 
+// Generating a 16384 bits key takes about ten minutes
+// Run with `cargo test --release -- --ignored rsa_generate_16384`
+#[test]
+#[ignore]
+fn rsa_generate_16384() {
+    use osshkeys::cipher::Cipher;
+
+    let key = KeyPair::generate(KeyType::RSA, 16384).unwrap();
+    assert_eq!(key.size(), 16384);
+    let pubkey = key.clone_public_key().unwrap();
+
+    let data = b"osshkeys 16384 bits test data";
+    let sig = key.sign(data).unwrap();
+    assert_eq!(sig.len(), 16384 / 8);
+    assert!(pubkey.verify(data, &sig).unwrap());
+    let sig_blob = key.sign_with_alg(data, None).unwrap();
+    assert_eq!(
+        pubkey.verify_detailed(data, &sig_blob).unwrap(),
+        VerifyOutcome::Valid
+    );
+
+    let pubkey_parsed = PublicKey::from_keystr(&pubkey.serialize().unwrap()).unwrap();
+    assert_eq!(pubkey_parsed.blob().unwrap(), pubkey.blob().unwrap());
+    assert_eq!(pubkey_parsed.size(), 16384);
+    assert_eq!(
+        pubkey_parsed.fingerprint(FingerprintHash::SHA256).unwrap(),
+        pubkey.fingerprint(FingerprintHash::SHA256).unwrap()
+    );
+    pubkey
+        .fingerprint_randomart(FingerprintHash::SHA256)
+        .unwrap();
+
+    for passphrase in [None, Some("16384")] {
+        let serialized = key
+            .serialize_openssh(passphrase, Cipher::Aes256_Ctr)
+            .unwrap();
+        let reloaded = KeyPair::from_keystr(&serialized, passphrase).unwrap();
+        assert_eq!(reloaded.size(), 16384);
+        assert_eq!(
+            reloaded.clone_public_key().unwrap().blob().unwrap(),
+            pubkey.blob().unwrap()
+        );
+        assert!(pubkey.verify(data, &reloaded.sign(data).unwrap()).unwrap());
+    }
+    let pem = key.serialize_pem(None).unwrap();
+    assert_eq!(KeyPair::from_keystr(&pem, None).unwrap().size(), 16384);
+}
+
 #[test]
 fn dsa_generate() {
     let key = KeyPair::generate(KeyType::DSA, 0).unwrap();
@@ -94,3 +142,137 @@ fn ed25519_generate() {
 fn ed25519_generate_invalid() {
     KeyPair::generate(KeyType::ED25519, 512).unwrap();
 }
+
+#[test]
+fn keypair_clone() {
+    for keytype in [KeyType::RSA, KeyType::DSA, KeyType::ECDSA, KeyType::ED25519] {
+        let mut key = KeyPair::generate(keytype, 0).unwrap();
+        *key.comment_mut() = "clone-test".into();
+        let cloned = key.clone();
+        assert_eq!(cloned.keytype(), keytype);
+        assert_eq!(cloned.comment(), "clone-test");
+        assert_eq!(
+            cloned.serialize_pem(None).unwrap(),
+            key.serialize_pem(None).unwrap()
+        );
+
+        let sign = cloned.sign(b"data").unwrap();
+        assert!(key.verify(b"data", &sign).unwrap());
+    }
+}
+
+#[test]
+fn host_keys_generate() {
+    let keys = generate_host_keys("testhost").unwrap();
+    let types: Vec<_> = keys.iter().map(|(t, _)| *t).collect();
+    assert_eq!(types, [KeyType::RSA, KeyType::ECDSA, KeyType::ED25519]);
+    for (keytype, key) in &keys {
+        assert_eq!(key.keytype(), *keytype);
+        assert_eq!(key.comment(), "root@testhost");
+    }
+    assert_eq!(keys[0].1.size(), 3072);
+    assert_eq!(keys[1].1.size(), 256);
+}
+
+#[test]
+fn rsa_generate_with_callback() {
+    use osshkeys::keys::rsa::*;
+    use std::ops::ControlFlow;
+
+    let mut found = 0;
+    let key = RsaKeyPair::generate_with_callback(2048, |progress| {
+        if progress.stage == 3 {
+            found += 1;
+        }
+        ControlFlow::Continue(())
+    })
+    .unwrap();
+    assert_eq!(key.size(), 2048);
+    assert_eq!(found, 2);
+}
+
+#[test]
+fn rsa_generate_with_callback_cancel() {
+    use osshkeys::error::ErrorKind;
+    use osshkeys::keys::rsa::*;
+    use std::ops::ControlFlow;
+
+    let mut calls = 0;
+    let err = RsaKeyPair::generate_with_callback(4096, |_| {
+        calls += 1;
+        ControlFlow::Break(())
+    })
+    .err()
+    .unwrap();
+    assert_eq!(err.kind(), ErrorKind::Cancelled);
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn ecdsa_generate_curve() {
+    use osshkeys::keys::ecdsa::EcCurve;
+
+    for (curve, name) in [
+        (EcCurve::Nistp256, "ecdsa-sha2-nistp256"),
+        (EcCurve::Nistp384, "ecdsa-sha2-nistp384"),
+        (EcCurve::Nistp521, "ecdsa-sha2-nistp521"),
+    ] {
+        let key = KeyPair::generate_ecdsa(curve).unwrap();
+        assert_eq!(key.keytype(), KeyType::ECDSA);
+        assert_eq!(key.size(), curve.size());
+        assert_eq!(key.keyname(), name);
+    }
+}
+
+#[test]
+fn generate_with_rng() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // Ed25519 keys are reproducible from the seeded RNG
+    let key1 =
+        KeyPair::generate_with_rng(KeyType::ED25519, 0, &mut StdRng::seed_from_u64(1)).unwrap();
+    let key2 =
+        KeyPair::generate_with_rng(KeyType::ED25519, 0, &mut StdRng::seed_from_u64(1)).unwrap();
+    let key3 =
+        KeyPair::generate_with_rng(KeyType::ED25519, 0, &mut StdRng::seed_from_u64(2)).unwrap();
+    assert_eq!(key1.blob().unwrap(), key2.blob().unwrap());
+    assert_ne!(key1.blob().unwrap(), key3.blob().unwrap());
+
+    // The OpenSSL keys only mix the RNG output in
+    let mut rng = StdRng::seed_from_u64(1);
+    for (keytype, bits) in [
+        (KeyType::RSA, 2048),
+        (KeyType::DSA, 1024),
+        (KeyType::ECDSA, 384),
+    ] {
+        let key = KeyPair::generate_with_rng(keytype, bits, &mut rng).unwrap();
+        assert_eq!(key.keytype(), keytype);
+        assert_eq!(key.size(), bits);
+    }
+
+    assert!(KeyPair::generate_with_rng(KeyType::ED25519, 512, &mut rng).is_err());
+    assert!(KeyPair::generate_with_rng(KeyType::XMSS, 0, &mut rng).is_err());
+}
+
+#[test]
+fn generate_default_bits() {
+    assert_eq!(KeyType::RSA.default_bits(), Some(3072));
+    assert_eq!(KeyType::DSA.default_bits(), Some(1024));
+    assert_eq!(KeyType::ECDSA.default_bits(), Some(256));
+    assert_eq!(KeyType::ED25519.default_bits(), None);
+
+    for (keytype, size) in [
+        (KeyType::RSA, 3072),
+        (KeyType::ECDSA, 256),
+        (KeyType::ED25519, 256),
+    ] {
+        let key = KeyPair::generate(keytype, None).unwrap();
+        assert_eq!(key.keytype(), keytype);
+        assert_eq!(key.size(), size);
+    }
+    assert_eq!(
+        KeyPair::generate(KeyType::ECDSA, None).unwrap().keyname(),
+        "ecdsa-sha2-nistp256"
+    );
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "serde")]
+extern crate osshkeys;
+
+use osshkeys::keys::*;
+use serde::de::value::{Error as ValueError, StrDeserializer};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+
+mod utils;
+
+// Serialize with the string serializer of `fmt::Formatter` provided by serde
+struct Serialized<'a>(&'a PublicKey);
+
+impl fmt::Display for Serialized<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Serialize::serialize(self.0, f)
+    }
+}
+
+fn deserialize(keystr: &str) -> Result<PublicKey, ValueError> {
+    let deserializer: StrDeserializer<ValueError> = keystr.into_deserializer();
+    PublicKey::deserialize(deserializer)
+}
+
+#[test]
+fn pubkey_serde_keystr() {
+    for name in [
+        "assets/openssh_rsa.pub",
+        "assets/openssh_dsa.pub",
+        "assets/openssh_ecdsa.pub",
+        "assets/openssh_ed25519.pub",
+    ] {
+        let keystr = fs::read_to_string(utils::locate_crate_files(name)).unwrap();
+        let pubkey = PublicKey::from_keystr(&keystr).unwrap();
+        let serialized = Serialized(&pubkey).to_string();
+        assert_eq!(serialized, keystr.trim_end());
+
+        let deserialized = deserialize(&serialized).unwrap();
+        utils::fingerprint_assert(&deserialized, &pubkey);
+        assert_eq!(deserialized.comment(), pubkey.comment());
+    }
+}
+
+#[test]
+fn pubkey_serde_invalid() {
+    assert!(deserialize("ssh-ed25519 AAAA").is_err());
+    assert!(deserialize("").is_err());
+
+    let keystr =
+        fs::read_to_string(utils::locate_crate_files("assets/openssh_ed25519.pub")).unwrap();
+    let mut pubkey = PublicKey::from_keystr(&keystr).unwrap();
+    *pubkey.comment_mut() = "line\nbreak".into();
+    let mut out = String::new();
+    assert!(fmt::write(&mut out, format_args!("{}", Serialized(&pubkey))).is_err());
+}
@@ -13,7 +13,7 @@ fn rsa_sign_verify() {
     fill_random(&mut data);
 
     let sign = key.sign(&data).unwrap();
-    assert_eq!(sign.len(), 256);
+    assert_eq!(sign.len(), 384);
     assert!(key.verify(&data, &sign).unwrap());
 }
 
@@ -27,7 +27,7 @@ fn rsa_sign_sha256_verify() {
     fill_random(&mut data);
 
     let sign = key.sign(&data).unwrap();
-    assert_eq!(sign.len(), 256);
+    assert_eq!(sign.len(), 384);
     assert!(key.verify(&data, &sign).unwrap());
 }
 
@@ -77,3 +77,217 @@ fn ed25519_sign_verify() {
     assert_eq!(sign.len(), 64);
     assert!(key.verify(&data, &sign).unwrap());
 }
+
+#[test]
+fn rsa_sha1_verify_policy() {
+    use osshkeys::error::ErrorKind;
+    use osshkeys::keys::rsa::*;
+
+    let mut data: [u8; 64] = [0; 64];
+    let mut key = KeyPair::generate(KeyType::RSA, 0).unwrap();
+    key.set_rsa_signature(RsaSignature::SHA1).unwrap();
+    fill_random(&mut data);
+
+    let sign = key.sign(&data).unwrap();
+    let pubkey = key.clone_public_key().unwrap();
+    assert!(pubkey
+        .verify_with_policy(&data, &sign, VerifyPolicy::default())
+        .unwrap());
+    assert_eq!(
+        pubkey
+            .verify_with_policy(&data, &sign, VerifyPolicy::strict())
+            .unwrap_err()
+            .kind(),
+        ErrorKind::WeakSignature
+    );
+
+    key.set_rsa_signature(RsaSignature::SHA2_256).unwrap();
+    let sign = key.sign(&data).unwrap();
+    let pubkey = key.clone_public_key().unwrap();
+    assert!(pubkey
+        .verify_with_policy(&data, &sign, VerifyPolicy::strict())
+        .unwrap());
+}
+
+#[test]
+fn dsa_verify_policy() {
+    use osshkeys::error::ErrorKind;
+
+    let mut data: [u8; 64] = [0; 64];
+    let key = KeyPair::generate(KeyType::DSA, 0).unwrap();
+    fill_random(&mut data);
+
+    let sign = key.sign(&data).unwrap();
+    let pubkey = key.clone_public_key().unwrap();
+    let policy = VerifyPolicy {
+        allow_dsa: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        pubkey
+            .verify_with_policy(&data, &sign, policy)
+            .unwrap_err()
+            .kind(),
+        ErrorKind::WeakSignature
+    );
+}
+
+// Re-encode a short DER signature with an extra leading zero in `r`
+fn overpad_der_sig(sig: &[u8]) -> Vec<u8> {
+    assert!(sig[0] == 0x30 && sig[1] < 0x7f && sig[2] == 0x02);
+    let mut padded = vec![0x30, sig[1] + 1, 0x02, sig[3] + 1, 0x00];
+    padded.extend_from_slice(&sig[4..]);
+    padded
+}
+
+#[test]
+fn dsa_verify_overpadded() {
+    let mut data: [u8; 64] = [0; 64];
+    let key = KeyPair::generate(KeyType::DSA, 0).unwrap();
+    fill_random(&mut data);
+
+    let sign = key.sign(&data).unwrap();
+    assert!(!key.verify(&data, &overpad_der_sig(&sign)).unwrap());
+}
+
+#[test]
+fn ecdsa_verify_overpadded() {
+    let mut data: [u8; 64] = [0; 64];
+    let key = KeyPair::generate(KeyType::ECDSA, 0).unwrap();
+    fill_random(&mut data);
+
+    let sign = key.sign(&data).unwrap();
+    assert!(!key.verify(&data, &overpad_der_sig(&sign)).unwrap());
+    let mut trailing = sign.clone();
+    trailing.push(0);
+    assert!(!key.verify(&data, &trailing).unwrap());
+}
+
+#[test]
+fn userauth_sign_data() {
+    use osshkeys::sshbuf::SshReadExt;
+    use std::io::Cursor;
+
+    let keypair = KeyPair::generate(KeyType::ED25519, 0).unwrap();
+    let blob = keypair.blob().unwrap();
+    let data = build_userauth_sign_data(b"sessid", "user", "ssh-connection", &blob, "ssh-ed25519")
+        .unwrap();
+
+    let mut reader = Cursor::new(&data);
+    assert_eq!(reader.read_string().unwrap(), b"sessid");
+    assert_eq!(reader.read_uint8().unwrap(), 50);
+    assert_eq!(reader.read_utf8().unwrap(), "user");
+    assert_eq!(reader.read_utf8().unwrap(), "ssh-connection");
+    assert_eq!(reader.read_utf8().unwrap(), "publickey");
+    assert!(reader.read_bool().unwrap());
+    assert_eq!(reader.read_utf8().unwrap(), "ssh-ed25519");
+    assert_eq!(reader.read_string().unwrap(), blob);
+    assert_eq!(reader.position() as usize, data.len());
+
+    let sig = keypair.sign(&data).unwrap();
+    assert!(keypair.verify(&data, &sig).unwrap());
+}
+
+#[test]
+fn verify_detailed_outcomes() {
+    use osshkeys::keys::rsa::RsaSignature;
+
+    let mut data: [u8; 64] = [0; 64];
+    fill_random(&mut data);
+
+    for keytype in [KeyType::RSA, KeyType::DSA, KeyType::ECDSA, KeyType::ED25519] {
+        let key = KeyPair::generate(keytype, 0).unwrap();
+        let pubkey = key.clone_public_key().unwrap();
+        let sig = key.sign_with_alg(&data, None).unwrap();
+        assert_eq!(
+            pubkey.verify_detailed(&data, &sig).unwrap(),
+            VerifyOutcome::Valid
+        );
+        assert_eq!(
+            pubkey.verify_detailed(b"other data", &sig).unwrap(),
+            VerifyOutcome::Invalid
+        );
+        assert_eq!(
+            pubkey
+                .verify_detailed(&data, &sig[..sig.len() - 1])
+                .unwrap(),
+            VerifyOutcome::MalformedSignature
+        );
+
+        // Replace the algorithm name
+        let mut mismatch = vec![0, 0, 0, 11];
+        mismatch.extend_from_slice(b"ssh-unknown");
+        let namelen = u32::from_be_bytes(sig[..4].try_into().unwrap()) as usize;
+        mismatch.extend_from_slice(&sig[4 + namelen..]);
+        assert_eq!(
+            pubkey.verify_detailed(&data, &mismatch).unwrap(),
+            VerifyOutcome::AlgorithmMismatch {
+                expected: pubkey.keyname(),
+                found: "ssh-unknown".into(),
+            }
+        );
+    }
+
+    // Any RSA hash is accepted by the RSA keys
+    let key = KeyPair::generate(KeyType::RSA, 0).unwrap();
+    let pubkey = key.clone_public_key().unwrap();
+    for alg in [
+        RsaSignature::SHA1,
+        RsaSignature::SHA2_256,
+        RsaSignature::SHA2_512,
+    ] {
+        let sig = key.sign_with_alg(&data, Some(alg)).unwrap();
+        assert_eq!(
+            pubkey.verify_detailed(&data, &sig).unwrap(),
+            VerifyOutcome::Valid
+        );
+    }
+
+    // A truncated Ed25519 signature inside a well-formed blob
+    let key = KeyPair::generate(KeyType::ED25519, 0).unwrap();
+    let mut sig = vec![0, 0, 0, 11];
+    sig.extend_from_slice(b"ssh-ed25519");
+    sig.extend_from_slice(&[0, 0, 0, 1, 0]);
+    assert_eq!(
+        key.clone_public_key()
+            .unwrap()
+            .verify_detailed(&data, &sig)
+            .unwrap(),
+        VerifyOutcome::MalformedSignature
+    );
+}
+
+#[test]
+fn verify_generic() {
+    use osshkeys::error::ErrorKind;
+
+    let mut data: [u8; 64] = [0; 64];
+    fill_random(&mut data);
+
+    let keys: Vec<KeyPair> = [KeyType::RSA, KeyType::DSA, KeyType::ECDSA, KeyType::ED25519]
+        .iter()
+        .map(|&keytype| KeyPair::generate(keytype, 0).unwrap())
+        .collect();
+    for (i, key) in keys.iter().enumerate() {
+        let pubkey = key.clone_public_key().unwrap();
+        let sig = Signature::from_blob(&key.sign_with_alg(&data, None).unwrap()).unwrap();
+        assert!(verify(&pubkey, &data, &sig).unwrap());
+        assert!(!verify(&pubkey, b"other data", &sig).unwrap());
+
+        // The signature of another key type
+        let other = &keys[(i + 1) % keys.len()];
+        let other_sig = Signature::from_blob(&other.sign_with_alg(&data, None).unwrap()).unwrap();
+        let err = verify(&pubkey, &data, &other_sig).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TypeNotMatch);
+        assert_eq!(err.failure(), Some(other_sig.algorithm()));
+    }
+
+    let sig = Signature::from_blob(b"\0\0\0\x0bssh-ed25519\0\0\0\x01").unwrap();
+    assert_eq!(sig.algorithm(), "ssh-ed25519");
+    let err = verify(&keys[3].clone_public_key().unwrap(), &data, &sig).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidFormat);
+    assert_eq!(
+        Signature::from_blob(b"\0\0").unwrap_err().kind(),
+        ErrorKind::InvalidFormat
+    );
+}
@@ -0,0 +1,297 @@
+extern crate osshkeys;
+
+use osshkeys::error::ErrorKind;
+use osshkeys::keys::*;
+use std::fs;
+
+mod utils;
+
+fn read_asset(name: &str) -> String {
+    fs::read_to_string(utils::locate_crate_files(name)).unwrap()
+}
+
+// The message signed by `ssh-keygen -Y sign -f assets/openssh_ed25519 -n file`
+const MESSAGE: &[u8] = b"hello sshsig\n";
+
+#[test]
+fn sshsig_sign_matches_openssh() {
+    let key = KeyPair::from_keystr(read_asset("assets/openssh_ed25519"), None).unwrap();
+    // Ed25519 signatures are deterministic
+    let sig = key
+        .sign_with_namespace(MESSAGE, "file", FingerprintHash::SHA512)
+        .unwrap();
+    assert_eq!(sig, read_asset("assets/sshsig_ed25519.sig"));
+}
+
+#[test]
+fn sshsig_sign_all_keys() {
+    for name in [
+        "assets/openssh_rsa",
+        "assets/openssh_dsa",
+        "assets/openssh_ecdsa",
+        "assets/openssh_ed25519",
+    ] {
+        let key = KeyPair::from_keystr(read_asset(name), None).unwrap();
+        for hash in [FingerprintHash::SHA256, FingerprintHash::SHA512] {
+            let sig = key.sign_with_namespace(MESSAGE, "git", hash).unwrap();
+            assert!(sig.starts_with("-----BEGIN SSH SIGNATURE-----\n"));
+            assert!(sig.ends_with("\n-----END SSH SIGNATURE-----\n"));
+            assert!(sig.lines().all(|l| l.len() <= 70));
+        }
+    }
+}
+
+#[test]
+fn sshsig_sign_invalid_args() {
+    let key = KeyPair::from_keystr(read_asset("assets/openssh_ed25519"), None).unwrap();
+    let err = key
+        .sign_with_namespace(MESSAGE, "", FingerprintHash::SHA512)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    for hash in [FingerprintHash::MD5, FingerprintHash::SHA1] {
+        let err = key.sign_with_namespace(MESSAGE, "file", hash).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    }
+
+    // The private key of a security key is kept by the token
+    let key = KeyPair::from_keystr(read_asset("assets/openssh_ed25519_sk"), None).unwrap();
+    let err = key
+        .sign_with_namespace(MESSAGE, "file", FingerprintHash::SHA512)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::SecurityKeyRequired);
+}
+
+#[test]
+fn sshsig_verify_openssh() {
+    let pubkey = PublicKey::from_keystr(&read_asset("assets/openssh_ed25519.pub")).unwrap();
+    let sig = read_asset("assets/sshsig_ed25519.sig");
+    assert!(pubkey.verify_sshsig(MESSAGE, "file", &sig).unwrap());
+    assert!(!pubkey
+        .verify_sshsig(b"other message", "file", &sig)
+        .unwrap());
+
+    // The namespace must be checked before trusting the signature
+    let err = pubkey.verify_sshsig(MESSAGE, "git", &sig).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NamespaceMismatch);
+    assert_eq!(err.failure(), Some("file"));
+
+    // Signed by another key
+    let other = PublicKey::from_keystr(&read_asset("assets/openssh_ecdsa.pub")).unwrap();
+    assert!(!other.verify_sshsig(MESSAGE, "file", &sig).unwrap());
+
+    // The CRLF line endings are accepted
+    let crlf = sig.replace('\n', "\r\n");
+    assert!(pubkey.verify_sshsig(MESSAGE, "file", &crlf).unwrap());
+}
+
+#[test]
+fn sshsig_sign_verify_roundtrip() {
+    for name in [
+        "assets/openssh_rsa",
+        "assets/openssh_dsa",
+        "assets/openssh_ecdsa",
+        "assets/openssh_ed25519",
+    ] {
+        let key = KeyPair::from_keystr(read_asset(name), None).unwrap();
+        let pubkey = key.clone_public_key().unwrap();
+        for hash in [FingerprintHash::SHA256, FingerprintHash::SHA512] {
+            let sig = key.sign_with_namespace(MESSAGE, "git", hash).unwrap();
+            assert!(pubkey.verify_sshsig(MESSAGE, "git", &sig).unwrap());
+            assert!(!pubkey.verify_sshsig(b"tampered", "git", &sig).unwrap());
+        }
+    }
+}
+
+#[test]
+fn sshsig_verify_malformed() {
+    let pubkey = PublicKey::from_keystr(&read_asset("assets/openssh_ed25519.pub")).unwrap();
+    let sig = read_asset("assets/sshsig_ed25519.sig");
+
+    let err = pubkey
+        .verify_sshsig(MESSAGE, "file", "not a signature")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidKeyFormat);
+    let truncated = sig.replace("-----END SSH SIGNATURE-----\n", "");
+    let err = pubkey
+        .verify_sshsig(MESSAGE, "file", &truncated)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidKeyFormat);
+    // The armored key file of another kind
+    let err = pubkey
+        .verify_sshsig(MESSAGE, "file", &read_asset("assets/openssh_ed25519"))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidKeyFormat);
+}
+
+#[test]
+fn sshsig_verify_cert() {
+    use osshkeys::format::sshsig::verify_sshsig_cert;
+    use osshkeys::keys::cert::{CertOptions, Certificate};
+
+    let cert = Certificate::from_keystring(&read_asset("assets/openssh_ed25519-cert.pub")).unwrap();
+    let ca = PublicKey::from_keystr(&read_asset("assets/cert_ca_ed25519.pub")).unwrap();
+    // Signed by `ssh-keygen -Y sign -f assets/openssh_ed25519-cert.pub -n git` with the key in the agent
+    let sig = read_asset("assets/sshsig_ed25519_cert.sig");
+    for principal in ["alice", "bob"] {
+        assert!(verify_sshsig_cert(&cert, &ca, principal, MESSAGE, "git", &sig).unwrap());
+    }
+    assert!(!verify_sshsig_cert(&cert, &ca, "alice", b"other data", "git", &sig).unwrap());
+    let err = verify_sshsig_cert(&cert, &ca, "carol", MESSAGE, "git", &sig).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::CertificateRejected);
+    let err = verify_sshsig_cert(&cert, &ca, "alice", MESSAGE, "file", &sig).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NamespaceMismatch);
+
+    // The signature with the plain certified key
+    let plain_sig = read_asset("assets/sshsig_ed25519.sig");
+    assert!(verify_sshsig_cert(&cert, &ca, "alice", MESSAGE, "file", &plain_sig).unwrap());
+
+    // Not issued by the CA
+    let other_ca = PublicKey::from_keystr(&read_asset("assets/openssh_rsa.pub")).unwrap();
+    assert!(!verify_sshsig_cert(&cert, &other_ca, "alice", MESSAGE, "git", &sig).unwrap());
+
+    // Expired
+    let ca_key = KeyPair::from_keystr(read_asset("assets/cert_ca_ed25519"), None).unwrap();
+    let opts = CertOptions {
+        valid_principals: vec!["alice".to_owned()],
+        valid_before: 1577836800,
+        ..Default::default()
+    };
+    let expired = ca_key.sign_certificate(cert.public_key(), opts).unwrap();
+    let err = verify_sshsig_cert(&expired, &ca, "alice", MESSAGE, "file", &plain_sig).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::CertificateRejected);
+
+    // A host certificate can't sign
+    let host_cert =
+        Certificate::from_keystring(&read_asset("assets/openssh_ecdsa-cert.pub")).unwrap();
+    let host_key = KeyPair::from_keystr(read_asset("assets/openssh_ecdsa"), None).unwrap();
+    let host_sig = host_key
+        .sign_with_namespace(MESSAGE, "git", FingerprintHash::SHA512)
+        .unwrap();
+    let err = verify_sshsig_cert(
+        &host_cert,
+        &other_ca,
+        "host.example.com",
+        MESSAGE,
+        "git",
+        &host_sig,
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::CertificateRejected);
+}
+
+#[test]
+fn sshsig_find_principals() {
+    use osshkeys::format::find_principals;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let ed25519 = PublicKey::from_keystr(&read_asset("assets/openssh_ed25519.pub")).unwrap();
+    let rsa = PublicKey::from_keystr(&read_asset("assets/openssh_rsa.pub")).unwrap();
+    let ecdsa = PublicKey::from_keystr(&read_asset("assets/openssh_ecdsa.pub")).unwrap();
+    let ed25519_str = ed25519.serialize().unwrap();
+    let rsa_str = rsa.serialize().unwrap();
+    let allowed_signers = [
+        "# The allowed signers".to_owned(),
+        format!("x@example.com unknown-option {}", ed25519_str),
+        format!(
+            "carol@example.com namespaces=\"git\",valid-after=\"20200101\" {}",
+            ed25519_str
+        ),
+        format!(
+            "dave@example.com valid-before=\"20200101Z\" {}",
+            ed25519_str
+        ),
+        format!("erin@example.com cert-authority {}", rsa_str),
+        format!(
+            "alice@example.com,bob@example.com {} alice's key",
+            ed25519_str
+        ),
+        format!("\"frank@example.com\" {}", rsa_str),
+    ]
+    .join("\n");
+
+    // Checked against `ssh-keygen -Y find-principals -Overify-time=...`
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+    let jan2024 = at(1704067200);
+    let jan2019 = at(1546300800);
+    assert_eq!(
+        find_principals(&allowed_signers, &ed25519, jan2024),
+        ["carol@example.com"]
+    );
+    assert_eq!(
+        find_principals(&allowed_signers, &ed25519, jan2019),
+        ["dave@example.com"]
+    );
+    assert_eq!(
+        find_principals(&allowed_signers, &rsa, jan2024),
+        ["frank@example.com"]
+    );
+    assert!(find_principals(&allowed_signers, &ecdsa, jan2024).is_empty());
+
+    let unrestricted = allowed_signers.replace("namespaces=\"git\",valid-after", "valid-before");
+    assert_eq!(
+        find_principals(&unrestricted, &ed25519, jan2024),
+        ["alice@example.com", "bob@example.com"]
+    );
+}
+
+#[test]
+fn sshsig_verify_file() {
+    use osshkeys::format::sshsig::verify_sshsig_file;
+
+    let pubkey = PublicKey::from_keystr(&read_asset("assets/openssh_ed25519.pub")).unwrap();
+    let sig_path = utils::locate_crate_files("assets/sshsig_ed25519.sig");
+    let dir = utils::create_tmp_folder();
+    let data_path = dir.join("message");
+    fs::write(&data_path, MESSAGE).unwrap();
+    assert!(verify_sshsig_file(&pubkey, &data_path, &sig_path, "file").unwrap());
+    let err = verify_sshsig_file(&pubkey, &data_path, &sig_path, "git").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NamespaceMismatch);
+
+    fs::write(&data_path, b"other message").unwrap();
+    assert!(!verify_sshsig_file(&pubkey, &data_path, &sig_path, "file").unwrap());
+
+    let missing = dir.join("missing");
+    let err = verify_sshsig_file(&pubkey, &missing, &sig_path, "file").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::IOError);
+    let err = verify_sshsig_file(&pubkey, &data_path, &missing, "file").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::IOError);
+    utils::remove_tmp_folder(dir);
+}
+
+#[test]
+fn sshsig_check_novalidate() {
+    use osshkeys::format::sshsig::check_sshsig_novalidate;
+
+    let pubkey = PublicKey::from_keystr(&read_asset("assets/openssh_ed25519.pub")).unwrap();
+    let sig = read_asset("assets/sshsig_ed25519.sig");
+    let signer = check_sshsig_novalidate(MESSAGE, "file", &sig)
+        .unwrap()
+        .unwrap();
+    assert!(signer == pubkey);
+    assert!(check_sshsig_novalidate(b"other message", "file", &sig)
+        .unwrap()
+        .is_none());
+    let err = check_sshsig_novalidate(MESSAGE, "git", &sig).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::NamespaceMismatch);
+    let err = check_sshsig_novalidate(MESSAGE, "file", "not a signature")
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidKeyFormat);
+
+    // The signature made with a certificate is checked with the certified key
+    let cert_sig = read_asset("assets/sshsig_ed25519_cert.sig");
+    let signer = check_sshsig_novalidate(MESSAGE, "git", &cert_sig)
+        .unwrap()
+        .unwrap();
+    assert!(signer == pubkey);
+
+    // Any key can pass the check, the signer is not trusted
+    let key = KeyPair::from_keystr(read_asset("assets/openssh_ecdsa"), None).unwrap();
+    let sig = key
+        .sign_with_namespace(MESSAGE, "file", FingerprintHash::SHA256)
+        .unwrap();
+    let signer = check_sshsig_novalidate(MESSAGE, "file", &sig)
+        .unwrap()
+        .unwrap();
+    assert!(signer == key.clone_public_key().unwrap());
+}
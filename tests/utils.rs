@@ -3,6 +3,7 @@
 extern crate hex;
 extern crate osshkeys;
 
+use base64::prelude::*;
 use osshkeys::keys::*;
 use rand::prelude::*;
 use std::fs;
@@ -68,3 +69,9 @@ pub fn locate_crate_files<P: AsRef<Path>>(path: P) -> PathBuf {
     abspath.push(path);
     abspath
 }
+
+// Decode the binary data inside the OpenSSH PEM armor, e.g. of the keys written by ssh-keygen
+pub fn openssh_binary(keystr: &str) -> Vec<u8> {
+    let b64: String = keystr.lines().filter(|l| !l.starts_with("-----")).collect();
+    BASE64_STANDARD.decode(b64).unwrap()
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "weak-keys")]
+extern crate osshkeys;
+
+use osshkeys::error::ErrorKind;
+use osshkeys::keys::*;
+use osshkeys::weakkeys::{
+    blocklist_fingerprint, debian_blocklist_name, is_debian_weak_in, Blocklist,
+};
+use std::fs;
+
+mod utils;
+
+fn load_pubkey(name: &str) -> PublicKey {
+    PublicKey::from_keystr(&fs::read_to_string(utils::locate_crate_files(name)).unwrap()).unwrap()
+}
+
+#[test]
+fn blocklist_fingerprint_format() {
+    let pubkey = load_pubkey("assets/openssh_ed25519_enc.pub");
+    // MD5:d2:95:52:b0:c8:7d:7f:f1:ac:b3:c2:22:9e:78:33:21 without the first 12 digits
+    assert_eq!(
+        blocklist_fingerprint(&pubkey).unwrap(),
+        "7ff1acb3c2229e783321"
+    );
+}
+
+#[test]
+fn blocklist_contains() {
+    let blocked = load_pubkey("assets/openssh_ed25519_enc.pub");
+    let other = load_pubkey("assets/openssh_rsa.pub");
+
+    let list: Blocklist = "# blacklist.ED25519-256\n\n7ff1acb3c2229e783321\n00000000000000000000\n"
+        .parse()
+        .unwrap();
+    assert_eq!(list.len(), 2);
+    assert!(list.contains(&blocked).unwrap());
+    assert!(!list.contains(&other).unwrap());
+
+    // The full fingerprint is also accepted
+    let list: Blocklist = "d29552b0c87d7ff1acb3c2229e783321".parse().unwrap();
+    assert!(list.contains(&blocked).unwrap());
+
+    // The same check from the key
+    assert!(blocked.is_in_blocklist(&list).unwrap());
+    assert!(!other.is_in_blocklist(&list).unwrap());
+
+    let mut list = Blocklist::new();
+    assert!(list.is_empty());
+    list.insert(&other).unwrap();
+    assert!(list.contains(&other).unwrap());
+    assert!(!list.contains(&blocked).unwrap());
+}
+
+#[test]
+fn blocklist_invalid_entry() {
+    for entry in ["7ff1acb3c2229e78332", "7ff1acb3c2229e78332g", "d2:95:52:b0"] {
+        assert_eq!(
+            entry.parse::<Blocklist>().unwrap_err().kind(),
+            ErrorKind::InvalidFormat
+        );
+    }
+}
+
+#[test]
+fn blocklist_debian_dir() {
+    let rsa = load_pubkey("assets/openssh_rsa.pub");
+    let rsa_other = load_pubkey("assets/openssh_rsa_enc.pub");
+    let dsa = load_pubkey("assets/openssh_dsa.pub");
+    let ed25519 = load_pubkey("assets/openssh_ed25519_enc.pub");
+    assert_eq!(
+        debian_blocklist_name(&rsa).unwrap(),
+        format!("blacklist.RSA-{}", rsa.size())
+    );
+    assert_eq!(debian_blocklist_name(&dsa).unwrap(), "blacklist.DSA-1024");
+    assert!(debian_blocklist_name(&ed25519).is_none());
+
+    let dir = std::env::temp_dir().join(format!("osshkeys-blocklist-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join(debian_blocklist_name(&rsa).unwrap()),
+        format!("# Test list\n{}\n", blocklist_fingerprint(&rsa).unwrap()),
+    )
+    .unwrap();
+    assert!(is_debian_weak_in(&rsa, &dir).unwrap());
+    // Only the keys in the list of their type and size are weak
+    if rsa_other.size() == rsa.size() {
+        assert!(!is_debian_weak_in(&rsa_other, &dir).unwrap());
+    }
+    // The other key types are not affected
+    assert!(!is_debian_weak_in(&ed25519, &dir).unwrap());
+    // The key can't be told to be safe without its list
+    assert_eq!(
+        is_debian_weak_in(&dsa, &dir).unwrap_err().kind(),
+        ErrorKind::IOError
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}